@@ -0,0 +1,107 @@
+use crate::{
+    analysis::automaton::{Automaton, Search},
+    parts::PathPattern,
+};
+
+/// Set operations on the paths patterns match. Paths are the ones starting with `/`.
+///
+/// For patterns too complex to explore the answers err on the side of overlap:
+/// `subsumes` gives `false` and `intersects` gives `true`.
+impl<'a> PathPattern<'a> {
+    /// Whether this pattern matches every path the other one does.
+    pub fn subsumes(&self, other: &PathPattern<'_>) -> bool {
+        search(self, other, |this, other| other && !this) == Search::NotFound
+    }
+
+    /// Whether some path is matched by both patterns.
+    pub fn intersects(&self, other: &PathPattern<'_>) -> bool {
+        search(self, other, |this, other| this && other) != Search::NotFound
+    }
+
+    /// Whether the patterns match exactly the same paths, e.g. `/a` and `/a*`.
+    pub fn is_equivalent(&self, other: &PathPattern<'_>) -> bool {
+        search(self, other, |this, other| this != other) == Search::NotFound
+    }
+
+    /// The shortest path this pattern matches.
+    pub fn example(&self) -> Option<String> {
+        Automaton::new(Some(self.as_str())).find(|m| m[0]).found()
+    }
+
+    /// The shortest path this pattern matches and the other one doesn't.
+    pub fn example_not_matched_by(&self, other: &PathPattern<'_>) -> Option<String> {
+        search(self, other, |this, other| this && !other).found()
+    }
+
+    /// The shortest path both patterns match.
+    pub fn example_matched_by_both(&self, other: &PathPattern<'_>) -> Option<String> {
+        search(self, other, |this, other| this && other).found()
+    }
+}
+
+fn search<P>(this: &PathPattern<'_>, other: &PathPattern<'_>, mut predicate: P) -> Search
+where
+    P: FnMut(bool, bool) -> bool,
+{
+    Automaton::new(vec![this.as_str(), other.as_str()]).find(|m| predicate(m[0], m[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(pattern: &str) -> PathPattern<'_> {
+        PathPattern::new(pattern)
+    }
+
+    #[test]
+    fn subsumes() {
+        assert!(p("/").subsumes(&p("/a")));
+        assert!(p("/a").subsumes(&p("/a/b")));
+        assert!(p("/a").subsumes(&p("/a*.php$")));
+        assert!(p("/*.php").subsumes(&p("/*.php$")));
+        assert!(p("*").subsumes(&p("/x$")));
+        assert!(p("/a").subsumes(&p("")));
+        assert!(!p("/a/b").subsumes(&p("/a")));
+        assert!(!p("/*.php$").subsumes(&p("/*.php")));
+        assert!(!p("").subsumes(&p("/")));
+        assert!(p("/a").is_equivalent(&p("/a*")));
+        assert!(!p("/a").is_equivalent(&p("/a$")));
+    }
+
+    #[test]
+    fn intersects() {
+        assert!(p("/a").intersects(&p("/*b")));
+        assert!(p("/*.php$").intersects(&p("/admin")));
+        assert!(!p("/a").intersects(&p("/b")));
+        assert!(!p("/a$").intersects(&p("/a/")));
+        assert!(!p("").intersects(&p("/")));
+        assert_eq!(
+            Some("/admin.php".to_string()),
+            p("/*.php$").example_matched_by_both(&p("/admin"))
+        );
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(Some("/a".to_string()), p("/a").example());
+        assert_eq!(Some("/.pdf".to_string()), p("/*.pdf$").example());
+        assert_eq!(None, p("").example());
+        assert_eq!(Some("/".to_string()), p("*").example());
+        assert_eq!(
+            Some("/ab".to_string()),
+            p("/a").example_not_matched_by(&p("/a$"))
+        );
+        assert_eq!(
+            Some("/private".to_string()),
+            p("/private").example_not_matched_by(&p("/private/"))
+        );
+        assert_eq!(None, p("/a/b").example_not_matched_by(&p("/a")));
+
+        for (a, b) in [("/a*c", "/ab"), ("/*.php", "/x*"), ("/$", "*")] {
+            if let Some(path) = p(a).example_not_matched_by(&p(b)) {
+                assert!(p(a).matches(&path) && !p(b).matches(&path));
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+/// Gives up on rule sets whose product has more states than this.
+const MAX_STATES: usize = 100_000;
+
+/// Bytes tried for the characters no pattern mentions, in order of preference.
+const FILLERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-_~.";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Token {
+    Byte(u8),
+    Star,
+}
+
+/// A path pattern as a sequence of tokens, prefix patterns end with `Star`.
+#[derive(Clone, Debug)]
+struct Compiled {
+    tokens: Vec<Token>,
+    /// An empty pattern matches nothing.
+    never: bool,
+}
+
+impl Compiled {
+    fn new(pattern: &str) -> Self {
+        let (body, anchored) = match pattern.strip_suffix('$') {
+            Some(body) => (body, true),
+            None => (pattern, false),
+        };
+        let mut tokens = Vec::new();
+        for &b in body.as_bytes() {
+            match b {
+                b'*' if tokens.last() == Some(&Token::Star) => {}
+                b'*' => tokens.push(Token::Star),
+                b => tokens.push(Token::Byte(b)),
+            }
+        }
+        if !anchored && tokens.last() != Some(&Token::Star) {
+            tokens.push(Token::Star);
+        }
+        Compiled {
+            tokens,
+            never: pattern.is_empty(),
+        }
+    }
+}
+
+/// The outcome of a search for a path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Search {
+    Found(String),
+    NotFound,
+    /// The patterns have too many combined states to explore.
+    Unknown,
+}
+
+impl Search {
+    pub fn found(self) -> Option<String> {
+        match self {
+            Search::Found(path) => Some(path),
+            Search::NotFound | Search::Unknown => None,
+        }
+    }
+}
+
+/// Runs several path patterns side by side, as one deterministic automaton.
+///
+/// A state holds, for every pattern, the set of positions in it reachable by the path so far.
+#[derive(Clone, Debug)]
+pub(crate) struct Automaton {
+    patterns: Vec<Compiled>,
+    /// Where the positions of each pattern start in a state.
+    offsets: Vec<usize>,
+    bits: usize,
+    alphabet: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct State(Vec<u64>);
+
+impl State {
+    fn get(&self, bit: usize) -> bool {
+        self.0[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    fn set(&mut self, bit: usize) {
+        self.0[bit / 64] |= 1 << (bit % 64);
+    }
+}
+
+impl Automaton {
+    pub fn new<'p, I>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = &'p str>,
+    {
+        let patterns: Vec<Compiled> = patterns.into_iter().map(Compiled::new).collect();
+        let mut offsets = Vec::with_capacity(patterns.len());
+        let mut bits = 0;
+        let mut used = [false; 256];
+        used[b'/' as usize] = true;
+        for pattern in &patterns {
+            offsets.push(bits);
+            bits += pattern.tokens.len() + 1;
+            for token in &pattern.tokens {
+                if let Token::Byte(b) = *token {
+                    used[b as usize] = true;
+                }
+            }
+        }
+        // Any other byte behaves the same, one of them stands for all.
+        let filler = FILLERS
+            .iter()
+            .chain(&(0x21..0x7f).collect::<Vec<u8>>())
+            .find(|&&b| !used[b as usize] && b != b'#')
+            .cloned();
+        let alphabet = filler
+            .into_iter()
+            .chain((0..=255u8).filter(|&b| used[b as usize]))
+            .collect();
+        Automaton {
+            patterns,
+            offsets,
+            bits,
+            alphabet,
+        }
+    }
+
+    /// Finds the shortest path, starting with `/`, whose matching patterns satisfy the predicate.
+    pub fn find<P>(&self, mut predicate: P) -> Search
+    where
+        P: FnMut(&[bool]) -> bool,
+    {
+        let start = self.step(&self.start(), b'/');
+        let mut states = vec![(start.clone(), usize::MAX, b'/')];
+        let mut seen = HashMap::new();
+        seen.insert(start, 0);
+        let mut queue = VecDeque::from(vec![0]);
+
+        while let Some(index) = queue.pop_front() {
+            let matches = self.matches(&states[index].0);
+            if predicate(&matches) {
+                if let Some(path) = self.path(&states, index) {
+                    return Search::Found(path);
+                }
+            }
+            for &b in &self.alphabet {
+                let next = self.step(&states[index].0, b);
+                if let Entry::Vacant(entry) = seen.entry(next.clone()) {
+                    if states.len() >= MAX_STATES {
+                        return Search::Unknown;
+                    }
+                    entry.insert(states.len());
+                    queue.push_back(states.len());
+                    states.push((next, index, b));
+                }
+            }
+        }
+        Search::NotFound
+    }
+
+    fn start(&self) -> State {
+        let mut state = State(vec![0; self.bits.div_ceil(64)]);
+        for (pattern, &offset) in self.patterns.iter().zip(&self.offsets) {
+            if !pattern.never {
+                self.reach(&mut state, pattern, offset, 0);
+            }
+        }
+        state
+    }
+
+    /// Adds a position and the ones after the stars following it.
+    fn reach(&self, state: &mut State, pattern: &Compiled, offset: usize, mut pos: usize) {
+        loop {
+            state.set(offset + pos);
+            match pattern.tokens.get(pos) {
+                Some(Token::Star) => pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn step(&self, state: &State, b: u8) -> State {
+        let mut next = State(vec![0; state.0.len()]);
+        for (pattern, &offset) in self.patterns.iter().zip(&self.offsets) {
+            for (pos, token) in pattern.tokens.iter().enumerate() {
+                if !state.get(offset + pos) {
+                    continue;
+                }
+                match *token {
+                    Token::Star => self.reach(&mut next, pattern, offset, pos),
+                    Token::Byte(c) if c == b => self.reach(&mut next, pattern, offset, pos + 1),
+                    Token::Byte(_) => {}
+                }
+            }
+        }
+        next
+    }
+
+    fn matches(&self, state: &State) -> Vec<bool> {
+        self.patterns
+            .iter()
+            .zip(&self.offsets)
+            .map(|(pattern, &offset)| state.get(offset + pattern.tokens.len()))
+            .collect()
+    }
+
+    fn path(&self, states: &[(State, usize, u8)], mut index: usize) -> Option<String> {
+        let mut bytes = Vec::new();
+        while index != usize::MAX {
+            let (_, parent, b) = states[index];
+            bytes.push(b);
+            index = parent;
+        }
+        bytes.reverse();
+        String::from_utf8(bytes).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parts::PathPattern;
+
+    #[test]
+    fn agrees_with_matches() {
+        let patterns = ["/fish", "/*.php$", "/a*b*c", "*x$", "", "/$"];
+        let automaton = Automaton::new(patterns.iter().cloned());
+        for path in [
+            "/",
+            "/fish/x",
+            "/index.php",
+            "/index.php5",
+            "/abc",
+            "/aXbYc/x",
+            "/box",
+        ] {
+            let state = path
+                .bytes()
+                .fold(automaton.start(), |state, b| automaton.step(&state, b));
+            let expected: Vec<bool> = patterns
+                .iter()
+                .map(|pattern| PathPattern::new(*pattern).matches(path))
+                .collect();
+            assert_eq!(expected, automaton.matches(&state), "{}", path);
+        }
+    }
+
+    #[test]
+    fn find() {
+        let automaton = Automaton::new(vec!["/a*b$", "/a"]);
+        assert_eq!(
+            Search::Found("/ab".into()),
+            automaton.find(|m| m[0] && m[1])
+        );
+        assert_eq!(Search::Found("/".into()), automaton.find(|m| !m[1]));
+        assert_eq!(Search::NotFound, automaton.find(|m| m[0] && !m[1]));
+    }
+}
@@ -0,0 +1,661 @@
+use std::{borrow::Cow, collections::BTreeSet, error::Error, fmt};
+
+use unicase::UniCase;
+
+use crate::{
+    analysis::{
+        automaton::{Automaton, Search},
+        redundancy::minimize_rules,
+    },
+    matcher::RuleMatching,
+    parts::*,
+    policy::AgentPolicy,
+};
+
+/// Which verdict a combination of two policies keeps where they disagree.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Combination {
+    /// A path is allowed only if both policies allow it.
+    Restrictive,
+    /// A path is allowed if either policy allows it.
+    Permissive,
+}
+
+/// Two rule lists no combined rules were found for.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Inexpressible {
+    /// A path the closest rules found decide the wrong way,
+    /// `None` if the rules were too complex to check.
+    pub path: Option<String>,
+}
+
+impl fmt::Display for Inexpressible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.as_ref() {
+            Some(path) => write!(f, "the combined rules can't decide {} correctly", path),
+            None => f.write_str("the rules are too complex to combine"),
+        }
+    }
+}
+
+impl Error for Inexpressible {}
+
+/// Rules deciding every path the most restrictive way of the two lists.
+///
+/// See `combine_rules`.
+pub fn restrict_rules(
+    left: &[Rule<'_>],
+    right: &[Rule<'_>],
+) -> Result<Vec<Rule<'static>>, Inexpressible> {
+    combine_rules(left, right, Combination::Restrictive)
+}
+
+/// Rules deciding every path the most permissive way of the two lists.
+///
+/// See `combine_rules`.
+pub fn relax_rules(
+    left: &[Rule<'_>],
+    right: &[Rule<'_>],
+) -> Result<Vec<Rule<'static>>, Inexpressible> {
+    combine_rules(left, right, Combination::Permissive)
+}
+
+/// Combines two rule lists read by the longest match.
+///
+/// Rules keep their patterns. As the longest match ranks rules by the length of their
+/// pattern, a rule which has to outrank a rule of the other list is padded with `*`,
+/// which lengthens it without changing the paths it matches: combining `Disallow: /internal/`
+/// with `Allow: /internal/status` restrictively gives `Disallow: /internal/*******`.
+///
+/// The rules come longest first, so the first match decides the same way. They are checked
+/// against both lists for every path, an error is returned where the longest match can't
+/// express the combination in these patterns, e.g. exceptions of both lists nested in each other.
+pub fn combine_rules(
+    left: &[Rule<'_>],
+    right: &[Rule<'_>],
+    combination: Combination,
+) -> Result<Vec<Rule<'static>>, Inexpressible> {
+    // The verdict which wins a disagreement.
+    let strong = combination == Combination::Permissive;
+    let mut entries: Vec<Entry> = Vec::new();
+    for (side, rules) in [left, right].iter().enumerate() {
+        for rule in rules.iter().filter(|rule| !rule.path.is_empty()) {
+            entries.push(Entry {
+                side,
+                allow: rule.allow,
+                source: rule.path.as_str().to_string(),
+                pattern: rule.path.as_str().to_string(),
+                len: rule.path.specificity(),
+                dropped: false,
+            });
+        }
+    }
+
+    // A permissive combination only disallows what both lists disallow,
+    // each disallow is narrowed to where it overlaps a disallow of the other list.
+    if combination == Combination::Permissive {
+        let mut narrowed: Vec<Entry> = Vec::new();
+        for entry in entries.iter() {
+            let others = entries
+                .iter()
+                .filter(|other| other.side != entry.side && !other.allow);
+            let patterns: Vec<String> = match entry.allow {
+                true => vec![entry.pattern.clone()],
+                false => others
+                    .filter_map(|other| intersection(&entry.pattern, &other.pattern))
+                    .collect(),
+            };
+            for pattern in patterns {
+                if !narrowed
+                    .iter()
+                    .any(|other| other.side == entry.side && other.pattern == pattern)
+                {
+                    narrowed.push(Entry {
+                        len: PathPattern::new(pattern.as_str()).specificity(),
+                        pattern,
+                        ..entry.clone()
+                    });
+                }
+            }
+        }
+        entries = narrowed;
+    }
+
+    let overlaps: Vec<Vec<bool>> = entries
+        .iter()
+        .map(|a| {
+            let a = PathPattern::new(a.pattern.as_str());
+            entries
+                .iter()
+                .map(|b| a.intersects(&PathPattern::new(b.pattern.as_str())))
+                .collect()
+        })
+        .collect();
+    let original: Vec<usize> = entries
+        .iter()
+        .map(|entry| PathPattern::new(entry.source.as_str()).specificity())
+        .collect();
+
+    let mut changed = true;
+    let mut rounds = 0;
+    while changed && rounds < entries.len() * entries.len() + 1 {
+        changed = false;
+        rounds += 1;
+
+        // The winning verdict outranks the other list wherever they overlap.
+        for s in 0..entries.len() {
+            for w in 0..entries.len() {
+                let (es, ew) = (&entries[s], &entries[w]);
+                if es.allow != strong
+                    || ew.allow == strong
+                    || es.side == ew.side
+                    || es.dropped
+                    || ew.dropped
+                    || !overlaps[s][w]
+                    || !ew.beats(es)
+                    || overruled(&entries, &original, s, w)
+                {
+                    continue;
+                }
+                let len = ew.len + !es.allow as usize;
+                match pad(&es.pattern, len) {
+                    Some(_) => entries[s].len = len,
+                    None => entries[w].dropped = true,
+                }
+                changed = true;
+            }
+        }
+
+        // Exceptions within a list are kept where that doesn't outrank the other list.
+        for w in 0..entries.len() {
+            for s in 0..entries.len() {
+                let (es, ew) = (&entries[s], &entries[w]);
+                if es.allow != strong
+                    || ew.allow == strong
+                    || es.side != ew.side
+                    || es.dropped
+                    || ew.dropped
+                    || !overlaps[s][w]
+                    || !beats(ew.allow, original[w], es.allow, original[s])
+                    || ew.beats(es)
+                {
+                    continue;
+                }
+                let len = es.len + !ew.allow as usize;
+                let keeps_order = entries.iter().enumerate().all(|(i, other)| {
+                    other.allow != strong
+                        || other.dropped
+                        || !overlaps[i][w]
+                        || match other.side == ew.side {
+                            true => {
+                                !beats(other.allow, original[i], ew.allow, original[w])
+                                    || beats(other.allow, other.len, ew.allow, len)
+                            }
+                            false => beats(other.allow, other.len, ew.allow, len),
+                        }
+                });
+                if keeps_order && pad(&ew.pattern, len).is_some() {
+                    entries[w].len = len;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    // Whatever couldn't be ordered gives way to the winning verdict.
+    for w in 0..entries.len() {
+        let ew = &entries[w];
+        if ew.allow == strong || ew.dropped {
+            continue;
+        }
+        let outranks = entries.iter().enumerate().any(|(s, es)| {
+            es.allow == strong
+                && !es.dropped
+                && es.side != ew.side
+                && overlaps[s][w]
+                && ew.beats(es)
+                && !overruled(&entries, &original, s, w)
+        });
+        if outranks {
+            entries[w].dropped = true;
+        }
+    }
+
+    let mut rules: Vec<(usize, Rule<'static>)> = Vec::new();
+    for entry in entries.iter().filter(|entry| !entry.dropped) {
+        let rule = Rule::new(
+            entry.allow,
+            pad(&entry.pattern, entry.len).unwrap_or_default(),
+        );
+        if !rules.iter().any(|(_, other)| *other == rule) {
+            rules.push((entry.len, rule));
+        }
+    }
+    rules.sort_by(|(a_len, a), (b_len, b)| {
+        b_len
+            .cmp(a_len)
+            .then(b.allow.cmp(&a.allow))
+            .then_with(|| a.path.as_str().cmp(b.path.as_str()))
+    });
+    let rules: Vec<_> = rules.into_iter().map(|(_, rule)| rule).collect();
+    let rules = minimize_rules(&rules, RuleMatching::LongestMatch);
+    verify(left, right, &rules, combination).map(|_| rules)
+}
+
+/// Checks that the combined rules decide every path like the combination of both lists.
+fn verify(
+    left: &[Rule<'_>],
+    right: &[Rule<'_>],
+    rules: &[Rule<'_>],
+    combination: Combination,
+) -> Result<(), Inexpressible> {
+    let automaton = Automaton::new(
+        left.iter()
+            .chain(right)
+            .chain(rules)
+            .map(|rule| rule.path.as_str()),
+    );
+    let search = automaton.find(|matches| {
+        let (left_matches, matches) = matches.split_at(left.len());
+        let (right_matches, matches) = matches.split_at(right.len());
+        let decide = |matching: RuleMatching, rules: &[Rule<'_>], matches: &[bool]| {
+            matching.decide(rules.iter().zip(matches.iter().cloned()))
+        };
+        let (a, b) = (
+            decide(RuleMatching::LongestMatch, left, left_matches),
+            decide(RuleMatching::LongestMatch, right, right_matches),
+        );
+        let expected = match combination {
+            Combination::Restrictive => a && b,
+            Combination::Permissive => a || b,
+        };
+        expected != decide(RuleMatching::LongestMatch, rules, matches)
+            || expected != decide(RuleMatching::FirstMatch, rules, matches)
+    });
+    match search {
+        Search::NotFound => Ok(()),
+        Search::Found(path) => Err(Inexpressible { path: Some(path) }),
+        Search::Unknown => Err(Inexpressible { path: None }),
+    }
+}
+
+impl<'a> Robots<'a> {
+    /// A robots.txt which allows every agent only what both files allow it.
+    ///
+    /// Crawl delays and request rates take the slower one, sitemaps are merged.
+    /// Fails where the rules of an agent can't be combined, see `combine_rules`.
+    pub fn restrict_with(&self, other: &Robots<'_>) -> Result<Robots<'static>, Inexpressible> {
+        combine(self, other, Combination::Restrictive)
+    }
+
+    /// A robots.txt which allows every agent what either file allows it.
+    ///
+    /// Crawl delays and request rates take the faster one, sitemaps are merged.
+    /// Fails where the rules of an agent can't be combined, see `combine_rules`.
+    pub fn relax_with(&self, other: &Robots<'_>) -> Result<Robots<'static>, Inexpressible> {
+        combine(self, other, Combination::Permissive)
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    side: usize,
+    allow: bool,
+    /// The pattern in its list.
+    source: String,
+    /// The pattern in the result, narrower in a permissive combination.
+    pattern: String,
+    /// The specificity the rule is given in the result.
+    len: usize,
+    dropped: bool,
+}
+
+impl Entry {
+    fn beats(&self, other: &Entry) -> bool {
+        beats(self.allow, self.len, other.allow, other.len)
+    }
+}
+
+/// Whether a rule outranks another one under the longest match.
+fn beats(allow: bool, len: usize, other_allow: bool, other_len: usize) -> bool {
+    (len, allow) > (other_len, other_allow)
+}
+
+/// Whether the list of rule `s` decides against it wherever rule `w` of the other list matches,
+/// so `s` needn't outrank `w`.
+fn overruled(entries: &[Entry], original: &[usize], s: usize, w: usize) -> bool {
+    let (es, ew) = (&entries[s], &entries[w]);
+    entries.iter().enumerate().any(|(i, other)| {
+        other.side == es.side
+            && other.allow != es.allow
+            && beats(other.allow, original[i], es.allow, original[s])
+            && PathPattern::new(other.source.as_str())
+                .subsumes(&PathPattern::new(ew.pattern.as_str()))
+    })
+}
+
+/// A pattern matching exactly the paths both patterns match, where one is easily found.
+///
+/// That is the narrower one of nested patterns, or a prefix followed by a pattern
+/// starting with `/*`, e.g. `/a*b` for `/a` and `/*b`.
+fn intersection(a: &str, b: &str) -> Option<String> {
+    let (pa, pb) = (PathPattern::new(a), PathPattern::new(b));
+    if pb.subsumes(&pa) {
+        return Some(a.to_string());
+    }
+    if pa.subsumes(&pb) {
+        return Some(b.to_string());
+    }
+    let literal = |pattern: &str| !pattern.contains('*') && !pattern.ends_with('$');
+    let (prefix, rest) = match (literal(a), literal(b)) {
+        (true, false) => (a, b.strip_prefix("/*")?),
+        (false, true) => (b, a.strip_prefix("/*")?),
+        _ => return None,
+    };
+    // The rest mustn't match within the prefix, or the paths doing so are missed.
+    let first = rest.bytes().next().filter(|&b| b != b'*' && b != b'$')?;
+    match prefix.bytes().skip(1).any(|b| b == first) {
+        true => None,
+        false => Some(format!("{}*{}", prefix, rest)),
+    }
+}
+
+/// The pattern made `len` long without changing the paths it matches.
+fn pad(pattern: &str, len: usize) -> Option<String> {
+    let extra = match len.checked_sub(pattern.len()) {
+        Some(0) | None => return Some(pattern.to_string()),
+        Some(extra) => "*".repeat(extra),
+    };
+    match pattern.ends_with('$') {
+        false => Some(format!("{}{}", pattern, extra)),
+        true => pattern
+            .find('*')
+            .map(|i| format!("{}{}{}", &pattern[..i], extra, &pattern[i..])),
+    }
+}
+
+fn combine(
+    left: &Robots<'_>,
+    right: &Robots<'_>,
+    combination: Combination,
+) -> Result<Robots<'static>, Inexpressible> {
+    let mut agents: Vec<&str> = Vec::new();
+    for section in left.sections.iter().chain(&right.sections) {
+        for agent in &section.useragents {
+            if !agents
+                .iter()
+                .any(|other| UniCase::new(*other) == UniCase::new(agent.as_ref()))
+            {
+                agents.push(agent);
+            }
+        }
+    }
+
+    let section = |agent: &str| {
+        let left = AgentPolicy::new(left, agent, Default::default());
+        let right = AgentPolicy::new(right, agent, Default::default());
+        combine_groups(&left, &right, combination)
+    };
+    let mut default_section = section("*")?;
+    default_section.useragents.insert(Cow::Borrowed("*"));
+    let mut sections: Vec<Section<'static>> = Vec::new();
+    for agent in agents {
+        let section = section(agent)?;
+        if same_group(&section, &default_section) {
+            continue;
+        }
+        match sections
+            .iter_mut()
+            .find(|other| same_group(other, &section))
+        {
+            Some(other) => other.push_ua(agent.to_string()),
+            None => {
+                let mut section = section;
+                section.push_ua(agent.to_string());
+                sections.push(section);
+            }
+        }
+    }
+
+    default_section.sitemaps = left
+        .sections
+        .iter()
+        .chain(Some(&left.default_section))
+        .chain(&right.sections)
+        .chain(Some(&right.default_section))
+        .flat_map(|section| section.sitemaps.iter().cloned())
+        .collect();
+    Ok(Robots {
+        default_section,
+        sections,
+        host: left
+            .host
+            .as_ref()
+            .or(right.host.as_ref())
+            .map(|host| Cow::Owned(host.to_string())),
+        comments: Vec::new(),
+    })
+}
+
+fn combine_groups(
+    left: &AgentPolicy<'_>,
+    right: &AgentPolicy<'_>,
+    combination: Combination,
+) -> Result<Section<'static>, Inexpressible> {
+    let mut rules = combine_rules(&left.rules, &right.rules, combination)?;
+    if rules.is_empty() {
+        rules.push(Rule::disallow(""));
+    }
+    let pick = |a: Option<f64>, b: Option<f64>| match (a, b, combination) {
+        (Some(a), Some(b), Combination::Restrictive) => Some(a.max(b)),
+        (Some(a), Some(b), Combination::Permissive) => Some(a.min(b)),
+        (a, b, Combination::Restrictive) => a.or(b),
+        (_, _, Combination::Permissive) => None,
+    };
+    let interval = |rate: Option<RequestRate>| rate.map(|rate| rate.into_duration().as_secs_f64());
+    let req_rate = match pick(interval(left.req_rate), interval(right.req_rate)) {
+        Some(seconds) if interval(left.req_rate) == Some(seconds) => left.req_rate,
+        Some(_) => right.req_rate,
+        None => None,
+    };
+    let length = |time: Option<VisitTime>| {
+        time.map(|time| match (time.end + 24 * 60 - time.start) % (24 * 60) {
+            0 => 24. * 60.,
+            minutes => f64::from(minutes),
+        })
+    };
+    // The shorter window is the more restrictive one.
+    let visit_time = match pick(
+        length(left.visit_time).map(|minutes| -minutes),
+        length(right.visit_time).map(|minutes| -minutes),
+    ) {
+        Some(minutes) if length(left.visit_time) == Some(-minutes) => left.visit_time,
+        Some(_) => right.visit_time,
+        None => None,
+    };
+
+    // Preferences both files state are kept, restricting also keeps all refusals,
+    // erring toward refusal where a file permits a use for some paths only.
+    let mut usage: Vec<UsagePreference<'static>> = left
+        .usage
+        .iter()
+        .filter(|pref| right.usage.contains(pref))
+        .map(|pref| pref.clone().into_owned())
+        .collect();
+    if combination == Combination::Restrictive {
+        for pref in left.usage.iter().chain(&right.usage) {
+            let mut refusal = pref.clone().into_owned();
+            refusal.signals.retain(|&(_, allowed)| !allowed);
+            if !refusal.signals.is_empty() && !usage.contains(&refusal) {
+                usage.push(refusal);
+            }
+        }
+    }
+
+    Ok(Section {
+        crawl_delay: pick(left.crawl_delay, right.crawl_delay),
+        req_rate,
+        visit_time,
+        rules,
+        usage,
+        sitemaps: BTreeSet::new(),
+        useragents: BTreeSet::new(),
+        comments: Vec::new(),
+    })
+}
+
+/// Whether the groups are the same but for their user agents.
+fn same_group(a: &Section<'_>, b: &Section<'_>) -> bool {
+    a.crawl_delay == b.crawl_delay
+        && a.req_rate == b.req_rate
+        && a.visit_time == b.visit_time
+        && a.rules == b.rules
+        && a.usage == b.usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PATHS: &[&str] = &[
+        "/",
+        "/internal/",
+        "/internal/status",
+        "/internal/status/x",
+        "/shop",
+        "/shop/public",
+        "/shop/public/private",
+        "/docs/a.pdf",
+        "/docs/a.pdfx",
+        "/tmp",
+    ];
+
+    fn rules(input: &str) -> Vec<Rule<'static>> {
+        Robots::from_str_lossy(&format!("User-agent: *\n{}", input))
+            .into_owned()
+            .default_section
+            .rules
+    }
+
+    fn check(left: &str, right: &str) {
+        let (left, right) = (rules(left), rules(right));
+        let longest = RuleMatching::LongestMatch;
+        for &combination in &[Combination::Restrictive, Combination::Permissive] {
+            let rules = combine_rules(&left, &right, combination).unwrap();
+            for path in PATHS {
+                let (a, b) = (
+                    longest.check_path(&left, path),
+                    longest.check_path(&right, path),
+                );
+                let expected = match combination {
+                    Combination::Restrictive => a && b,
+                    Combination::Permissive => a || b,
+                };
+                assert_eq!(expected, longest.check_path(&rules, path), "{}", path);
+                assert_eq!(
+                    expected,
+                    RuleMatching::FirstMatch.check_path(&rules, path),
+                    "{}",
+                    path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn combined() {
+        check(
+            "Disallow: /internal/",
+            "Disallow: /shop\nAllow: /shop/public",
+        );
+        check(
+            "Disallow: /internal/",
+            "Disallow: /\nAllow: /internal/status\nAllow: /shop",
+        );
+        check(
+            "Disallow: /shop\nAllow: /shop/public\nDisallow: /docs/*.pdf$",
+            "Allow: /shop\nDisallow: /shop/public/\nDisallow: /docs",
+        );
+        check("Disallow: /tmp$\nAllow: /", "Disallow: /*");
+    }
+
+    #[test]
+    fn narrowed() {
+        let (left, right) = ("Disallow: /a\nDisallow: /a/b", "Allow: /a\nDisallow: /*b");
+        check(left, right);
+        let relaxed = relax_rules(&rules(left), &rules(right)).unwrap();
+        assert_eq!(rules("Disallow: /a*b"), relaxed);
+        assert!(!RuleMatching::LongestMatch.check_path(&relaxed, "/ab"));
+
+        assert_eq!(
+            Err(Inexpressible {
+                path: Some("/xy".into())
+            }),
+            relax_rules(&rules("Disallow: /*x"), &rules("Disallow: /*y"))
+        );
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(Some("/a**".to_string()), super::pad("/a", 4));
+        assert_eq!(Some("/a**.php$".to_string()), super::pad("/a*.php$", 9));
+        assert_eq!(None, super::pad("/a$", 5));
+        assert_eq!(Some("/a$".to_string()), super::pad("/a$", 2));
+    }
+
+    #[test]
+    fn robots() {
+        let platform = Robots::from_str_lossy(
+            r#"
+User-agent: *
+Disallow: /internal/
+Crawl-delay: 2
+Sitemap: http://example.com/platform.xml
+"#,
+        );
+        let customer = Robots::from_str_lossy(
+            r#"
+User-agent: *
+Disallow: /shop
+Allow: /shop/public
+Allow: /internal/status
+Crawl-delay: 5
+
+User-agent: badbot
+Disallow: /
+Sitemap: http://example.com/sitemap.xml
+"#,
+        );
+        assert_eq!(
+            platform.restrict_with(&customer).unwrap().to_string(),
+            "\
+User-agent: badbot
+Disallow: /
+Crawl-delay: 2
+
+User-agent: *
+Disallow: /internal/*******
+Allow: /shop/public
+Disallow: /shop
+Crawl-delay: 5
+Sitemap: http://example.com/platform.xml
+Sitemap: http://example.com/sitemap.xml
+
+"
+        );
+        assert_eq!(
+            platform.relax_with(&customer).unwrap().to_string(),
+            "\
+User-agent: badbot
+Disallow: /internal/
+
+User-agent: *
+Disallow:
+Crawl-delay: 2
+Sitemap: http://example.com/platform.xml
+Sitemap: http://example.com/sitemap.xml
+
+"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    analysis::automaton::{Automaton, Search},
+    matcher::RuleMatching,
+    parts::*,
+    policy::Semantics,
+};
+
+/// A path decided differently by two policies.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Witness {
+    pub path: String,
+    /// Whether the left policy allows the path.
+    pub left: bool,
+    pub right: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Equivalence {
+    /// Every path is allowed by both or by neither.
+    Equivalent,
+    Different(Witness),
+    /// The rules are too complex to compare.
+    Unknown,
+}
+
+impl Equivalence {
+    pub fn is_equivalent(&self) -> bool {
+        *self == Equivalence::Equivalent
+    }
+
+    pub fn witness(&self) -> Option<&Witness> {
+        match self {
+            Equivalence::Different(witness) => Some(witness),
+            _ => None,
+        }
+    }
+}
+
+/// Checks whether two rule lists allow exactly the same paths.
+pub fn compare_rules(left: &[Rule<'_>], right: &[Rule<'_>], matching: RuleMatching) -> Equivalence {
+    let automaton = Automaton::new(left.iter().chain(right).map(|rule| rule.path.as_str()));
+    let decide = |matches: &[bool]| {
+        let (left_matches, right_matches) = matches.split_at(left.len());
+        (
+            matching.decide(left.iter().zip(left_matches.iter().cloned())),
+            matching.decide(right.iter().zip(right_matches.iter().cloned())),
+        )
+    };
+    match automaton.find(|matches| {
+        let (left, right) = decide(matches);
+        left != right
+    }) {
+        Search::Found(path) => {
+            let (left, right) = (
+                matching.check_path(left, &path),
+                matching.check_path(right, &path),
+            );
+            Equivalence::Different(Witness { path, left, right })
+        }
+        Search::NotFound => Equivalence::Equivalent,
+        Search::Unknown => Equivalence::Unknown,
+    }
+}
+
+impl<'a> Robots<'a> {
+    /// Checks whether `agent` here may fetch exactly what `other_agent` may in the other file.
+    ///
+    /// Either file may be this one, to compare two agents of the same file.
+    pub fn compare_policy(
+        &self,
+        agent: &str,
+        other: &Robots<'_>,
+        other_agent: &str,
+        semantics: Semantics,
+    ) -> Equivalence {
+        compare_rules(
+            &self.policy_for(agent, semantics.agents).rules,
+            &other.policy_for(other_agent, semantics.agents).rules,
+            semantics.rules,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(input: &str) -> Vec<Rule<'static>> {
+        Robots::from_str_lossy(&format!("User-agent: *\n{}", input))
+            .into_owned()
+            .default_section
+            .rules
+    }
+
+    #[test]
+    fn equivalent() {
+        let test = |left: &str, right: &str, matching| {
+            compare_rules(&rules(left), &rules(right), matching)
+        };
+        let first = RuleMatching::FirstMatch;
+        let longest = RuleMatching::LongestMatch;
+
+        assert!(test(
+            "Disallow: /a\nDisallow: /b",
+            "Disallow: /b\nDisallow: /a",
+            first
+        )
+        .is_equivalent());
+        assert!(test("Disallow: /a\nDisallow: /ab", "Disallow: /a", first).is_equivalent());
+        assert!(test("Disallow: /a*", "Disallow: /a", longest).is_equivalent());
+        assert!(test("Disallow:", "Allow: /", first).is_equivalent());
+        assert!(test(
+            "Disallow: /*.php$\nDisallow: /*.php",
+            "Disallow: /*.php",
+            longest
+        )
+        .is_equivalent());
+
+        // The order decides under the first match only.
+        let (a, b) = ("Allow: /a/b\nDisallow: /a", "Disallow: /a\nAllow: /a/b");
+        assert!(test(a, b, longest).is_equivalent());
+        assert_eq!(
+            Equivalence::Different(Witness {
+                path: "/a/b".into(),
+                left: true,
+                right: false,
+            }),
+            test(a, b, first)
+        );
+    }
+
+    #[test]
+    fn different() {
+        let test = |left: &str, right: &str| {
+            let witness = compare_rules(&rules(left), &rules(right), RuleMatching::LongestMatch);
+            witness.witness().unwrap().path.clone()
+        };
+        assert_eq!("/", test("Disallow: /", ""));
+        assert_eq!("/.php", test("Disallow: /*.php$", ""));
+        assert_eq!("/.phpa", test("Disallow: /*.php$", "Disallow: /*.php"));
+        assert_eq!("/x", test("Disallow: /x$$", "Disallow: /x$"));
+    }
+
+    #[test]
+    fn agents() {
+        let robots = Robots::from_str_lossy(
+            r#"
+User-agent: *
+Disallow: /private
+Disallow: /tmp
+
+User-agent: googlebot
+Disallow: /tmp
+Disallow: /private/
+Disallow: /private
+
+User-agent: bingbot
+Disallow: /private
+"#,
+        );
+        let semantics = Semantics::rfc9309();
+        assert!(robots
+            .compare_policy("Googlebot/2.1", &robots, "*", semantics)
+            .is_equivalent());
+        let result = robots.compare_policy("bingbot", &robots, "*", semantics);
+        assert_eq!("/tmp", result.witness().unwrap().path);
+
+        let changed = Robots::from_str_lossy("User-agent: *\nDisallow: /private\n");
+        let result = robots.compare_policy("*", &changed, "*", semantics);
+        assert_eq!(Some(false), result.witness().map(|witness| witness.left));
+    }
+}
@@ -0,0 +1,10 @@
+//! Static analysis of rules: comparing policies and patterns without fetching anything.
+
+pub mod algebra;
+pub(crate) mod automaton;
+pub mod combine;
+pub mod equivalence;
+pub mod redundancy;
+pub mod witness;
+
+pub use self::{combine::*, equivalence::*, redundancy::*, witness::*};
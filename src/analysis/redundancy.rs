@@ -0,0 +1,403 @@
+use std::{cell::OnceCell, collections::HashSet, fmt};
+
+use crate::{
+    analysis::automaton::{Automaton, Search},
+    matcher::RuleMatching,
+    parts::*,
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RuleIssue {
+    /// Removing the rule changes no decision, it repeats another rule or the default.
+    Redundant,
+    /// The rule never decides a path, rules with the opposite verdict always win over it.
+    Shadowed,
+    /// The same path is also listed with the opposite verdict, and that rule wins.
+    Conflict,
+}
+
+impl fmt::Display for RuleIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RuleIssue::Redundant => "redundant",
+            RuleIssue::Shadowed => "shadowed",
+            RuleIssue::Conflict => "conflicting",
+        })
+    }
+}
+
+/// A rule which can be removed without changing any decision.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RuleFinding {
+    /// The index of the rule in the list.
+    pub index: usize,
+    pub issue: RuleIssue,
+    /// The index of a single rule which covers this one, if there is one.
+    pub covered_by: Option<usize>,
+}
+
+/// Finds the rules which make no difference to the decisions.
+///
+/// Removing all of the reported rules at once still gives identical decisions.
+/// Most rules are settled by comparing them pairwise, the paths of the whole list
+/// are explored once for the others.
+pub fn analyze_rules(rules: &[Rule<'_>], matching: RuleMatching) -> Vec<RuleFinding> {
+    let paths = Paths::new(rules);
+    let mut kept = vec![true; rules.len()];
+    let mut findings = Vec::new();
+    for index in 0..rules.len() {
+        let rest = || (0..rules.len()).filter(|&i| kept[i] && i != index);
+        // Removing a rule which changes the decision of its own example is no use.
+        let decisive = example(&rules[index]).is_some_and(|path| {
+            let m: Vec<bool> = rules.iter().map(|rule| rule.path.matches(&path)).collect();
+            matching.decide(rules.iter().zip(m.iter().cloned()))
+                != matching.decide(rest().map(|i| (&rules[i], m[i])))
+        });
+        let removable = !decisive
+            && (is_covered(rules, &kept, index, matching)
+                || paths.sets().is_some_and(|sets| {
+                    sets.iter().all(|m| {
+                        matching.decide(rules.iter().zip(m.iter().cloned()))
+                            == matching.decide(rest().map(|i| (&rules[i], m[i])))
+                    })
+                }));
+        if !removable {
+            continue;
+        }
+        kept[index] = false;
+
+        let rule = &rules[index];
+        let conflict = (0..rules.len()).find(|&i| {
+            rules[i].allow != rule.allow
+                && rules[i].path == rule.path
+                && beats(rules, i, index, matching)
+        });
+        let finding = match conflict {
+            Some(other) if !rule.path.is_empty() => RuleFinding {
+                index,
+                issue: RuleIssue::Conflict,
+                covered_by: Some(other),
+            },
+            _ if is_overridden(rules, index, matching, &paths) => RuleFinding {
+                index,
+                issue: RuleIssue::Shadowed,
+                covered_by: (0..rules.len()).find(|&i| {
+                    rules[i].allow != rule.allow
+                        && beats(rules, i, index, matching)
+                        && subsumes(&rules[i].path, &rule.path)
+                }),
+            },
+            _ => RuleFinding {
+                index,
+                issue: RuleIssue::Redundant,
+                covered_by: (0..rules.len()).find(|&i| {
+                    kept[i] && rules[i].allow == rule.allow && subsumes(&rules[i].path, &rule.path)
+                }),
+            },
+        };
+        findings.push(finding);
+    }
+    findings
+}
+
+/// The rules without the ones `analyze_rules` reports, deciding every path the same way.
+pub fn minimize_rules<'a>(rules: &[Rule<'a>], matching: RuleMatching) -> Vec<Rule<'a>> {
+    let findings = analyze_rules(rules, matching);
+    rules
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !findings.iter().any(|finding| finding.index == *i))
+        .map(|(_, rule)| rule.clone())
+        .collect()
+}
+
+impl<'a> Section<'a> {
+    pub fn analyze_rules(&self, matching: RuleMatching) -> Vec<RuleFinding> {
+        analyze_rules(&self.rules, matching)
+    }
+
+    pub fn minimized_rules(&self, matching: RuleMatching) -> Vec<Rule<'a>> {
+        minimize_rules(&self.rules, matching)
+    }
+}
+
+/// Whether the rule at `i` takes precedence over the one at `j` when both match.
+fn beats(rules: &[Rule<'_>], i: usize, j: usize, matching: RuleMatching) -> bool {
+    match matching {
+        RuleMatching::FirstMatch => i < j,
+        RuleMatching::LongestMatch => {
+            let key = |rule: &Rule<'_>| (rule.path.specificity(), rule.allow);
+            let (a, b) = (key(&rules[i]), key(&rules[j]));
+            a > b || a == b && i < j
+        }
+    }
+}
+
+/// Whether some path the rule matches is decided against its verdict.
+fn is_overridden(rules: &[Rule<'_>], index: usize, matching: RuleMatching, paths: &Paths) -> bool {
+    let rule = &rules[index];
+    let opposing: Vec<usize> = (0..rules.len())
+        .filter(|&i| verdict(&rules[i]) != rule.allow && beats(rules, i, index, matching))
+        .filter(|&i| captures(rules, i, index, matching))
+        .collect();
+    if rule.path.is_empty() || opposing.is_empty() {
+        return false;
+    }
+    // An opposing rule matching all its paths decides them, unless a rule with the same
+    // verdict outranks that one.
+    if opposing.iter().any(|&i| {
+        subsumes(&rules[i].path, &rule.path)
+            && intersects(&rules[i].path, &rule.path)
+            && !(0..rules.len()).any(|k| {
+                k != index
+                    && verdict(&rules[k]) == rule.allow
+                    && beats(rules, k, i, matching)
+                    && captures(rules, k, index, matching)
+            })
+    }) {
+        return true;
+    }
+    let overridden_at = |path: String| matching.check_path(rules, &path) != rule.allow;
+    if example(rule).is_some_and(overridden_at) {
+        return true;
+    }
+    paths.sets().is_some_and(|sets| {
+        sets.iter()
+            .any(|m| m[index] && matching.decide(rules.iter().zip(m.iter().cloned())) != rule.allow)
+    })
+}
+
+/// Whether removing a non-empty rule keeps the decisions, as a kept rule matches all its paths
+/// and either outranks it or has the same verdict with no kept rule with the opposite one
+/// ranking between the two.
+fn is_covered(rules: &[Rule<'_>], kept: &[bool], index: usize, matching: RuleMatching) -> bool {
+    let rule = &rules[index];
+    let others = || (0..rules.len()).filter(move |&i| kept[i] && i != index);
+    let covers = |i: usize| match (matching, rules[i].path.is_empty()) {
+        (RuleMatching::FirstMatch, true) => true,
+        (_, true) => false,
+        _ => subsumes(&rules[i].path, &rule.path),
+    };
+    !rule.path.is_empty()
+        && others().any(|i| {
+            covers(i)
+                && (beats(rules, i, index, matching)
+                    || verdict(&rules[i]) == rule.allow
+                        && !others().any(|k| {
+                            k != i
+                                && verdict(&rules[k]) != rule.allow
+                                && beats(rules, k, i, matching)
+                                && beats(rules, index, k, matching)
+                                && captures(rules, k, index, matching)
+                        }))
+        })
+}
+
+/// The shortest path the rule matches.
+fn example(rule: &Rule<'_>) -> Option<String> {
+    match literal(&rule.path) {
+        Some(path) if path.starts_with('/') => Some(path.to_string()),
+        _ => rule.path.example(),
+    }
+}
+
+/// The verdict a rule gives where it decides, an empty one allows.
+fn verdict(rule: &Rule<'_>) -> bool {
+    rule.allow || rule.path.is_empty()
+}
+
+/// Whether the rule at `i` may decide a path the one at `j` matches.
+///
+/// An empty rule stops the first match at any path.
+fn captures(rules: &[Rule<'_>], i: usize, j: usize, matching: RuleMatching) -> bool {
+    match (matching, rules[i].path.is_empty()) {
+        (RuleMatching::FirstMatch, true) => true,
+        _ => intersects(&rules[i].path, &rules[j].path),
+    }
+}
+
+/// A pattern without wildcards, which matches the paths it is a prefix of.
+fn literal<'p>(pattern: &'p PathPattern<'_>) -> Option<&'p str> {
+    let pattern = pattern.as_str();
+    match pattern.is_empty() || pattern.contains('*') || pattern.ends_with('$') {
+        true => None,
+        false => Some(pattern),
+    }
+}
+
+/// `PathPattern::subsumes`, without an automaton for literal patterns.
+fn subsumes(a: &PathPattern<'_>, b: &PathPattern<'_>) -> bool {
+    match (literal(a), literal(b)) {
+        (Some(a), Some(b)) => b.starts_with(a),
+        _ => a.subsumes(b),
+    }
+}
+
+/// `PathPattern::intersects`, without an automaton for literal patterns.
+fn intersects(a: &PathPattern<'_>, b: &PathPattern<'_>) -> bool {
+    match (literal(a), literal(b)) {
+        (Some(a), Some(b)) => a.starts_with(b) || b.starts_with(a),
+        _ => a.intersects(b),
+    }
+}
+
+/// Which rules match together, over all paths, explored once and only when needed.
+struct Paths<'r, 'a> {
+    rules: &'r [Rule<'a>],
+    sets: OnceCell<Option<Vec<Vec<bool>>>>,
+}
+
+impl<'r, 'a> Paths<'r, 'a> {
+    fn new(rules: &'r [Rule<'a>]) -> Self {
+        Paths {
+            rules,
+            sets: OnceCell::new(),
+        }
+    }
+
+    /// Each distinct set of matching rules, `None` if there are too many to explore.
+    fn sets(&self) -> Option<&[Vec<bool>]> {
+        self.sets
+            .get_or_init(|| {
+                let automaton = Automaton::new(self.rules.iter().map(|rule| rule.path.as_str()));
+                let mut sets = HashSet::new();
+                match automaton.find(|m| {
+                    sets.insert(m.to_vec());
+                    false
+                }) {
+                    Search::Unknown => None,
+                    Search::Found(_) | Search::NotFound => Some(sets.into_iter().collect()),
+                }
+            })
+            .as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::equivalence::compare_rules;
+
+    fn rules(input: &str) -> Vec<Rule<'static>> {
+        Robots::from_str_lossy(&format!("User-agent: *\n{}", input))
+            .into_owned()
+            .default_section
+            .rules
+    }
+
+    fn finding(index: usize, issue: RuleIssue, covered_by: Option<usize>) -> RuleFinding {
+        RuleFinding {
+            index,
+            issue,
+            covered_by,
+        }
+    }
+
+    #[test]
+    fn redundant() {
+        let rules = rules("Disallow: /a\nDisallow: /a/b\nDisallow: /c*\nDisallow: /c");
+        assert_eq!(
+            vec![
+                finding(1, RuleIssue::Redundant, Some(0)),
+                finding(2, RuleIssue::Redundant, Some(3)),
+            ],
+            analyze_rules(&rules, RuleMatching::LongestMatch)
+        );
+        assert_eq!(
+            vec![
+                finding(1, RuleIssue::Redundant, Some(0)),
+                finding(2, RuleIssue::Redundant, Some(3)),
+            ],
+            analyze_rules(&rules, RuleMatching::FirstMatch)
+        );
+    }
+
+    #[test]
+    fn shadowed() {
+        let rules =
+            rules("Disallow: /shop\nAllow: /shop/cart\nDisallow: /docs\nAllow: /docs/*.pdf$");
+        assert_eq!(
+            vec![
+                finding(1, RuleIssue::Shadowed, Some(0)),
+                finding(3, RuleIssue::Shadowed, Some(2)),
+            ],
+            analyze_rules(&rules, RuleMatching::FirstMatch)
+        );
+        assert_eq!(
+            Vec::<RuleFinding>::new(),
+            analyze_rules(&rules, RuleMatching::LongestMatch)
+        );
+    }
+
+    #[test]
+    fn conflict() {
+        let rules = rules("Disallow: /page\nAllow: /page");
+        // With the disallow gone, the allow only repeats the default.
+        assert_eq!(
+            vec![
+                finding(0, RuleIssue::Conflict, Some(1)),
+                finding(1, RuleIssue::Redundant, None),
+            ],
+            analyze_rules(&rules, RuleMatching::LongestMatch)
+        );
+        assert_eq!(
+            vec![finding(1, RuleIssue::Conflict, Some(0))],
+            analyze_rules(&rules, RuleMatching::FirstMatch)
+        );
+    }
+
+    #[test]
+    fn many() {
+        let input: String = (0..100)
+            .map(|i| {
+                format!(
+                    "Disallow: /dir{0}/\nAllow: /dir{0}/public\nDisallow: /dir{0}/private\n",
+                    i
+                )
+            })
+            .collect();
+        let rules = rules(&format!("{}Disallow: /*.bak$\n", input));
+        assert_eq!(301, rules.len());
+        for &matching in &[RuleMatching::FirstMatch, RuleMatching::LongestMatch] {
+            let shadowed = matching == RuleMatching::FirstMatch;
+            let expected: Vec<_> = (0..100)
+                .flat_map(|i| {
+                    let public = finding(3 * i + 1, RuleIssue::Shadowed, Some(3 * i));
+                    let private = finding(3 * i + 2, RuleIssue::Redundant, Some(3 * i));
+                    match shadowed {
+                        true => vec![public, private],
+                        false => vec![private],
+                    }
+                })
+                .collect();
+            assert_eq!(expected, analyze_rules(&rules, matching));
+        }
+    }
+
+    #[test]
+    fn minimize() {
+        let section = Robots::from_str_lossy(
+            r#"
+User-agent: *
+Disallow: /private
+Disallow: /private/keys
+Allow: /private/keys
+Disallow: /tmp
+Allow: /public
+"#,
+        )
+        .default_section;
+        for &matching in &[RuleMatching::FirstMatch, RuleMatching::LongestMatch] {
+            let minimized = section.minimized_rules(matching);
+            assert!(compare_rules(&section.rules, &minimized, matching).is_equivalent());
+            assert_eq!(
+                vec!["/private", "/tmp"],
+                minimized
+                    .iter()
+                    .filter(|rule| !rule.allow)
+                    .map(|rule| rule.path.as_str())
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
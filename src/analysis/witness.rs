@@ -0,0 +1,278 @@
+use std::fmt;
+
+use crate::{
+    analysis::automaton::Automaton,
+    matcher::RuleMatching,
+    parts::*,
+    policy::{product_token, AgentMatching, Semantics},
+};
+
+/// An example path decided by a rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleWitness {
+    /// The index of the rule in the list.
+    pub index: usize,
+    /// `None` if the rule never decides a path.
+    pub path: Option<String>,
+    /// The decision the rule makes.
+    pub allowed: bool,
+}
+
+impl RuleWitness {
+    pub fn is_reachable(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+/// A path an `Allow` rule carves out of a `Disallow` rule which also matches it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Exception {
+    pub allow: usize,
+    pub disallow: usize,
+    pub path: String,
+}
+
+/// A row of a generated test table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestCase {
+    pub agent: String,
+    pub path: String,
+    pub allowed: bool,
+}
+
+impl fmt::Display for TestCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = match self.allowed {
+            true => "allowed",
+            false => "disallowed",
+        };
+        write!(f, "{}\t{}\t{}", self.agent, self.path, verdict)
+    }
+}
+
+/// An example path for each rule, on which that rule is the one deciding.
+pub fn rule_witnesses(rules: &[Rule<'_>], matching: RuleMatching) -> Vec<RuleWitness> {
+    let automaton = automaton(rules);
+    (0..rules.len())
+        .map(|index| RuleWitness {
+            index,
+            path: automaton
+                .find(|m| decider(rules, m, matching) == Some(index))
+                .found(),
+            allowed: rules[index].allow || rules[index].path.is_empty(),
+        })
+        .collect()
+}
+
+/// An example for every pair of an `Allow` rule and a `Disallow` rule it makes exceptions to.
+pub fn exceptions(rules: &[Rule<'_>], matching: RuleMatching) -> Vec<Exception> {
+    let automaton = automaton(rules);
+    let mut exceptions = Vec::new();
+    for (allow, _) in rules.iter().enumerate().filter(|(_, rule)| rule.allow) {
+        for (disallow, _) in rules.iter().enumerate().filter(|(_, rule)| !rule.allow) {
+            let path =
+                automaton.find(|m| m[disallow] && decider(rules, m, matching) == Some(allow));
+            if let Some(path) = path.found() {
+                exceptions.push(Exception {
+                    allow,
+                    disallow,
+                    path,
+                });
+            }
+        }
+    }
+    exceptions
+}
+
+impl<'a> Section<'a> {
+    pub fn rule_witnesses(&self, matching: RuleMatching) -> Vec<RuleWitness> {
+        rule_witnesses(&self.rules, matching)
+    }
+
+    pub fn exceptions(&self, matching: RuleMatching) -> Vec<Exception> {
+        exceptions(&self.rules, matching)
+    }
+}
+
+impl<'a> Robots<'a> {
+    /// Expected decisions for every group: a path for each reachable rule and each exception.
+    ///
+    /// Each group is checked through its first user agent which reaches it, and the decision
+    /// is that of everything the agent obeys. Groups no agent reaches are left out.
+    pub fn test_cases(&self, semantics: Semantics) -> Vec<TestCase> {
+        let mut cases = Vec::new();
+        let sections = self.sections.iter().map(Some).enumerate();
+        for (index, section) in sections.chain(Some((self.sections.len(), None))) {
+            let (section, agent) = match section {
+                Some(section) => match section
+                    .useragents
+                    .iter()
+                    .find(|agent| reaches(self, agent, index, semantics.agents))
+                {
+                    Some(agent) => (section, agent.to_string()),
+                    None => continue,
+                },
+                None => (&self.default_section, "*".to_string()),
+            };
+            let policy = self.policy_for(&agent, semantics.agents);
+            if section.is_default() != policy.is_default() {
+                continue;
+            }
+            let witnesses = section.rule_witnesses(semantics.rules).into_iter();
+            let exceptions = section.exceptions(semantics.rules).into_iter();
+            let paths = witnesses
+                .filter_map(|witness| witness.path)
+                .chain(exceptions.map(|exception| exception.path));
+            for path in paths {
+                if cases
+                    .iter()
+                    .any(|case: &TestCase| case.agent == agent && case.path == path)
+                {
+                    continue;
+                }
+                cases.push(TestCase {
+                    allowed: policy.check_path_with(&path, semantics.rules),
+                    agent: agent.clone(),
+                    path,
+                });
+            }
+        }
+        cases
+    }
+}
+
+/// Whether a crawler named as a user agent of the group at the index gets that group.
+fn reaches(robots: &Robots<'_>, agent: &str, index: usize, matching: AgentMatching) -> bool {
+    match matching {
+        AgentMatching::Substring => robots.choose_section_index(agent) == Some(index),
+        AgentMatching::ProductToken => !product_token(agent).is_empty(),
+    }
+}
+
+fn automaton(rules: &[Rule<'_>]) -> Automaton {
+    Automaton::new(rules.iter().map(|rule| rule.path.as_str()))
+}
+
+fn decider(rules: &[Rule<'_>], matches: &[bool], matching: RuleMatching) -> Option<usize> {
+    matching.decider(rules.iter().zip(matches.iter().cloned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ROBOTS: &str = r#"
+User-agent: *
+Disallow: /shop
+Allow: /shop/*.html$
+Disallow: /shop/cart
+
+User-agent: badbot
+Disallow: /
+"#;
+
+    #[test]
+    fn witnesses() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        let rules = &robots.default_section.rules;
+
+        let witnesses = rule_witnesses(rules, RuleMatching::LongestMatch);
+        let paths: Vec<_> = witnesses.iter().map(|w| w.path.as_deref()).collect();
+        assert_eq!(
+            vec![Some("/shop"), Some("/shop/.html"), Some("/shop/cart")],
+            paths
+        );
+        for witness in &witnesses {
+            let path = witness.path.as_ref().unwrap();
+            assert_eq!(
+                witness.allowed,
+                RuleMatching::LongestMatch.check_path(rules, path)
+            );
+        }
+
+        // The first match makes the later rules unreachable.
+        let witnesses = rule_witnesses(rules, RuleMatching::FirstMatch);
+        assert!(witnesses[0].is_reachable());
+        assert!(!witnesses[1].is_reachable());
+        assert!(!witnesses[2].is_reachable());
+    }
+
+    #[test]
+    fn exceptions() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        assert_eq!(
+            vec![
+                Exception {
+                    allow: 1,
+                    disallow: 0,
+                    path: "/shop/.html".into(),
+                },
+                Exception {
+                    allow: 1,
+                    disallow: 2,
+                    path: "/shop/cart.html".into(),
+                },
+            ],
+            robots
+                .default_section
+                .exceptions(RuleMatching::LongestMatch)
+        );
+        assert!(robots
+            .default_section
+            .exceptions(RuleMatching::FirstMatch)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cases() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        let table: Vec<String> = robots
+            .test_cases(Semantics::rfc9309())
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "badbot\t/\tdisallowed",
+                "*\t/shop\tdisallowed",
+                "*\t/shop/.html\tallowed",
+                "*\t/shop/cart\tdisallowed",
+                "*\t/shop/cart.html\tallowed",
+            ],
+            table
+        );
+    }
+
+    #[test]
+    fn test_cases_of_agents() {
+        let robots = Robots::from_str_lossy(
+            "\
+User-agent: bot
+Disallow: /a
+
+User-agent: badbot
+Disallow: /
+
+User-agent: bot
+Allow: /a
+",
+        );
+        let table = |semantics| -> Vec<String> {
+            robots
+                .test_cases(semantics)
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+        // The groups of `bot` are merged and the `Allow` wins the tie.
+        assert_eq!(
+            vec!["bot\t/a\tallowed", "badbot\t/\tdisallowed"],
+            table(Semantics::rfc9309())
+        );
+        // `badbot` and the second `bot` get the first group, the empty default rule decides.
+        assert_eq!(
+            vec!["bot\t/a\tdisallowed", "*\t/\tallowed"],
+            table(Semantics::legacy())
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::{error::Error, fmt};
+
+use crate::parts::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BuildErrorKind {
+    /// `Crawl-delay` is negative, infinite or NaN.
+    InvalidCrawlDelay(f64),
+    /// `Request-rate` with zero requests or zero seconds.
+    InvalidRequestRate(RequestRate),
+    /// A path which is not a valid pattern.
+    InvalidPath(String, PatternError),
+    /// A group without any `User-agent` line.
+    NoUserAgents,
+    /// A user agent which is empty or contains whitespace, `#` or `:`.
+    InvalidUserAgent(String),
+    /// A `rule_comment` with no rule after it in its group.
+    DanglingRuleComment(String),
+    /// A `trailing_comment` with no rule before it in its group.
+    DanglingTrailingComment(String),
+}
+
+impl fmt::Display for BuildErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildErrorKind::InvalidCrawlDelay(delay) => {
+                write!(f, "invalid crawl delay `{}`", delay)
+            }
+            BuildErrorKind::InvalidRequestRate(rate) => write!(
+                f,
+                "invalid request rate `{}/{}`",
+                rate.requests, rate.seconds
+            ),
+            BuildErrorKind::InvalidPath(path, error) => {
+                write!(f, "invalid path `{}`: {}", path, error)
+            }
+            BuildErrorKind::NoUserAgents => f.write_str("group has no user agents"),
+            BuildErrorKind::InvalidUserAgent(ua) => write!(f, "invalid user agent `{}`", ua),
+            BuildErrorKind::DanglingRuleComment(comment) => {
+                write!(f, "rule comment `{}` has no rule after it", comment)
+            }
+            BuildErrorKind::DanglingTrailingComment(comment) => {
+                write!(f, "trailing comment `{}` has no rule before it", comment)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildError {
+    /// The index of the group in `Robots::sections`, `None` for the default group.
+    pub section: Option<usize>,
+    pub kind: BuildErrorKind,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.section {
+            Some(index) => write!(f, "group #{}: {}", index, self.kind),
+            None => write!(f, "group `*`: {}", self.kind),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/// All the problems found by [`RobotsBuilder::try_build`](struct.RobotsBuilder.html#method.try_build).
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildErrors(pub Vec<BuildError>);
+
+impl BuildErrors {
+    pub fn errors(&self) -> &[BuildError] {
+        &self.0
+    }
+}
+
+impl IntoIterator for BuildErrors {
+    type Item = BuildError;
+    type IntoIter = ::std::vec::IntoIter<BuildError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl fmt::Display for BuildErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            error.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for BuildErrors {}
+
+pub(crate) fn validate(robots: &Robots<'_>) -> Vec<BuildError> {
+    let mut errors = Vec::new();
+    let sections = robots
+        .sections
+        .iter()
+        .enumerate()
+        .map(|(i, section)| (Some(i), section))
+        .chain(Some((None, &robots.default_section)));
+    for (index, section) in sections {
+        let mut push = |kind| {
+            errors.push(BuildError {
+                section: index,
+                kind,
+            })
+        };
+        if section.useragents.is_empty() {
+            push(BuildErrorKind::NoUserAgents);
+        }
+        for ua in &section.useragents {
+            let invalid = ua.is_empty()
+                || ua
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control() || c == '#' || c == ':');
+            if invalid {
+                push(BuildErrorKind::InvalidUserAgent(ua.to_string()));
+            }
+        }
+        for rule in &section.rules {
+            if let Err(error) = rule.path.validate() {
+                push(BuildErrorKind::InvalidPath(rule.path.to_string(), error));
+            }
+        }
+        if let Some(delay) = section.crawl_delay {
+            if !delay.is_finite() || delay < 0. {
+                push(BuildErrorKind::InvalidCrawlDelay(delay));
+            }
+        }
+        if let Some(rate) = section.req_rate {
+            if rate.requests == 0 || rate.seconds == 0 {
+                push(BuildErrorKind::InvalidRequestRate(rate));
+            }
+        }
+    }
+    errors
+}
@@ -1,6 +1,8 @@
+pub mod error;
 pub mod robots;
 pub mod section;
 
+pub use self::error::*;
 pub use self::robots::*;
 pub use self::section::*;
 
@@ -26,6 +28,22 @@ Request-rate: 9/20
 Sitemap: http://example.com/sitemap.xml
 
 Host: example.com
+"#;
+
+    static SAMPLE_3: &str = r#"
+# Do not edit, generated from policy.toml
+
+# Partners
+User-agent: cybermapper
+# Maps are expensive to render
+Disallow: /cyberworld/map/ # TICKET-12
+Allow: /cyberworld/map/index.html
+
+User-agent: *
+Disallow: /
+# Everyone may see the front page
+Allow: /$
+
 "#;
 
     fn assert_eq(robots: &Robots, sample: &str) {
@@ -88,4 +106,106 @@ Host: example.com
 
         assert_eq(&robots, SAMPLE_2);
     }
+
+    #[test]
+    fn build_3_comments() {
+        let robots = Robots::builder()
+            .comment("Do not edit, generated from policy.toml")
+            .start_section("cybermapper")
+            .comment("Partners")
+            .rule_comment("Maps are expensive to render")
+            .disallow("/cyberworld/map/")
+            .trailing_comment("TICKET-12")
+            .allow("/cyberworld/map/index.html")
+            .end_section()
+            .with_section("*", |section| {
+                section
+                    .disallow("/")
+                    .rule_comment("Everyone may see the front page")
+                    .allow("/$")
+            })
+            .build();
+
+        assert_eq(&robots, SAMPLE_3);
+    }
+
+    #[test]
+    fn try_build_ok() {
+        let robots = Robots::builder()
+            .with_section("cybermapper", |section| section.disallow(""))
+            .with_section("*", |section| section.disallow("/cyberworld/map/"))
+            .try_build()
+            .unwrap();
+
+        assert_eq(&robots, SAMPLE_1);
+    }
+
+    #[test]
+    fn try_build_errors() {
+        let errors = Robots::builder()
+            .with_section("bad bot", |section| {
+                section
+                    .disallow("private")
+                    .allow("*.html")
+                    .crawl_delay(-3.0)
+                    .request_rate(0, 10)
+            })
+            .section(Section::empty())
+            .with_section("*", |section| section.crawl_delay(f64::NAN))
+            .try_build()
+            .unwrap_err();
+
+        let kinds: Vec<_> = errors
+            .errors()
+            .iter()
+            .map(|e| (e.section, e.kind.to_string()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (Some(0), "invalid user agent `bad bot`".to_string()),
+                (
+                    Some(0),
+                    "invalid path `private`: must start with `/` or `*`".to_string()
+                ),
+                (Some(0), "invalid crawl delay `-3`".to_string()),
+                (Some(0), "invalid request rate `0/10`".to_string()),
+                (Some(1), "group has no user agents".to_string()),
+                (None, "invalid crawl delay `NaN`".to_string()),
+            ]
+        );
+        assert_eq!(
+            "group #1: group has no user agents",
+            errors.errors()[4].to_string()
+        );
+    }
+
+    #[test]
+    fn try_build_dangling_rule_comment() {
+        let builder = Robots::builder()
+            .with_section("cybermapper", |section| {
+                section.disallow("").rule_comment("Maps next")
+            })
+            .with_section("*", |section| section.disallow("/cyberworld/map/"));
+        assert_eq(&builder.clone().build(), SAMPLE_1);
+
+        let errors = builder.try_build().unwrap_err();
+        assert_eq!(
+            "group #0: rule comment `Maps next` has no rule after it",
+            errors.to_string()
+        );
+
+        let builder = Robots::builder()
+            .with_section("cybermapper", |section| {
+                section.trailing_comment("Maps").disallow("")
+            })
+            .with_section("*", |section| section.disallow("/cyberworld/map/"));
+        assert_eq(&builder.clone().build(), SAMPLE_1);
+
+        let errors = builder.try_build().unwrap_err();
+        assert_eq!(
+            "group #0: trailing comment `Maps` has no rule before it",
+            errors.to_string()
+        );
+    }
 }
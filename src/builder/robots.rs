@@ -7,6 +7,21 @@ pub struct RobotsBuilder<'a> {
     default_section: Option<Section<'a>>,
     sections: Vec<Section<'a>>,
     host: Option<Cow<'a, str>>,
+    comments: Vec<Cow<'a, str>>,
+    /// The problems found while building the groups, for `try_build`.
+    errors: Vec<BuildError>,
+}
+
+impl<'a> From<Robots<'a>> for RobotsBuilder<'a> {
+    fn from(robots: Robots<'a>) -> Self {
+        RobotsBuilder {
+            default_section: Some(robots.default_section),
+            sections: robots.sections,
+            host: robots.host,
+            comments: robots.comments,
+            errors: Vec::new(),
+        }
+    }
 }
 
 impl<'a> RobotsBuilder<'a> {
@@ -26,6 +41,24 @@ impl<'a> RobotsBuilder<'a> {
         self
     }
 
+    /// Adds a group, reporting the problems found while building it in `try_build`.
+    pub(crate) fn section_with_errors(
+        mut self,
+        section: Section<'a>,
+        errors: Vec<BuildErrorKind>,
+    ) -> Self {
+        let index = match section.is_default() {
+            true => None,
+            false => Some(self.sections.len()),
+        };
+        self.errors
+            .extend(errors.into_iter().map(|kind| BuildError {
+                section: index,
+                kind,
+            }));
+        self.section(section)
+    }
+
     pub fn start_section<U>(self, ua: U) -> SectionBuilder<'a>
     where
         U: Into<Cow<'static, str>>,
@@ -51,15 +84,33 @@ impl<'a> RobotsBuilder<'a> {
         self
     }
 
+    /// Adds a comment line to the top of the file.
+    pub fn comment<C>(mut self, comment: C) -> Self
+    where
+        C: Into<Cow<'a, str>>,
+    {
+        self.comments.push(comment.into());
+        self
+    }
+
+    /// The robots.txt, leaving out rule and trailing comments with no rule to go with.
     pub fn build(self) -> Robots<'a> {
-        let default_section = match self.default_section {
-            Some(default_section) => default_section,
-            None => Section::default(),
-        };
         Robots {
-            default_section,
+            default_section: self.default_section.unwrap_or_default(),
             sections: self.sections,
             host: self.host,
+            comments: self.comments,
+        }
+    }
+
+    /// Like `build`, but checks the result for mistakes and reports all of them.
+    pub fn try_build(mut self) -> Result<Robots<'a>, BuildErrors> {
+        let mut errors = std::mem::take(&mut self.errors);
+        let robots = self.build();
+        errors.extend(validate(&robots));
+        match errors.is_empty() {
+            true => Ok(robots),
+            false => Err(BuildErrors(errors)),
         }
     }
 }
@@ -12,6 +12,11 @@ pub struct SectionBuilder<'a> {
     sitemaps: BTreeSet<Url>,
     crawl_delay: Option<f64>,
     req_rate: Option<RequestRate>,
+    visit_time: Option<VisitTime>,
+    usage: Vec<UsagePreference<'a>>,
+    comments: Vec<Cow<'a, str>>,
+    rule_comments: Vec<Cow<'a, str>>,
+    errors: Vec<BuildErrorKind>,
 }
 
 impl<'a> SectionBuilder<'a> {
@@ -23,6 +28,11 @@ impl<'a> SectionBuilder<'a> {
             sitemaps: Default::default(),
             crawl_delay: None,
             req_rate: None,
+            visit_time: None,
+            usage: Default::default(),
+            comments: Default::default(),
+            rule_comments: Default::default(),
+            errors: Default::default(),
         }
     }
 
@@ -34,19 +44,60 @@ impl<'a> SectionBuilder<'a> {
         self
     }
 
-    pub fn disallow<P>(mut self, path: P) -> Self
+    pub fn disallow<P>(self, path: P) -> Self
     where
-        P: Into<Cow<'static, str>>,
+        P: Into<PathPattern<'static>>,
     {
-        self.rules.push(Rule::disallow(path));
+        self.rule(Rule::disallow(path))
+    }
+
+    pub fn allow<P>(self, path: P) -> Self
+    where
+        P: Into<PathPattern<'static>>,
+    {
+        self.rule(Rule::allow(path))
+    }
+
+    fn rule(mut self, mut rule: Rule<'a>) -> Self {
+        rule.comments.append(&mut self.rule_comments);
+        self.rules.push(rule);
         self
     }
 
-    pub fn allow<P>(mut self, path: P) -> Self
+    /// Adds a comment line to the top of the group.
+    pub fn comment<C>(mut self, comment: C) -> Self
     where
-        P: Into<Cow<'static, str>>,
+        C: Into<Cow<'a, str>>,
     {
-        self.rules.push(Rule::allow(path));
+        self.comments.push(comment.into());
+        self
+    }
+
+    /// Adds a comment line before the next `allow` or `disallow` rule.
+    ///
+    /// `try_build` reports the comments with no rule after them in the group.
+    pub fn rule_comment<C>(mut self, comment: C) -> Self
+    where
+        C: Into<Cow<'a, str>>,
+    {
+        self.rule_comments.push(comment.into());
+        self
+    }
+
+    /// Sets a comment at the end of the line of the last added rule.
+    ///
+    /// `try_build` reports it if there is no rule before it in the group.
+    pub fn trailing_comment<C>(mut self, comment: C) -> Self
+    where
+        C: Into<Cow<'a, str>>,
+    {
+        let comment = comment.into();
+        match self.rules.last_mut() {
+            Some(rule) => rule.trailing_comment = Some(comment),
+            None => self.errors.push(BuildErrorKind::DanglingTrailingComment(
+                comment.into_owned(),
+            )),
+        }
         self
     }
 
@@ -65,13 +116,33 @@ impl<'a> SectionBuilder<'a> {
         self
     }
 
-    pub fn end_section(self) -> RobotsBuilder<'a> {
-        self.robots.section(Section {
+    pub fn visit_time(mut self, visit_time: VisitTime) -> Self {
+        self.visit_time = Some(visit_time);
+        self
+    }
+
+    /// Adds a `Content-Signal` or `Content-Usage` line.
+    pub fn usage(mut self, preference: UsagePreference<'a>) -> Self {
+        self.usage.push(preference);
+        self
+    }
+
+    pub fn end_section(mut self) -> RobotsBuilder<'a> {
+        let section = Section {
             crawl_delay: self.crawl_delay,
             req_rate: self.req_rate,
+            visit_time: self.visit_time,
             rules: self.rules,
+            usage: self.usage,
             sitemaps: self.sitemaps,
             useragents: self.useragents,
-        })
+            comments: self.comments,
+        };
+        let dangling = self
+            .rule_comments
+            .into_iter()
+            .map(|comment| BuildErrorKind::DanglingRuleComment(comment.into_owned()));
+        self.errors.extend(dangling);
+        self.robots.section_with_errors(section, self.errors)
     }
 }
@@ -0,0 +1,1054 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::{Duration, SystemTime},
+};
+
+use url::{Position, Url};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    fetch::*,
+    matcher::RobotsMatcher,
+    payload::PayloadDiagnostic,
+    store::{PolicyStore, StoredPolicy},
+};
+
+/// The scheme, host and port a robots.txt file applies to.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Origin {
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Origin {
+    /// `None` if the URL is not HTTP(S).
+    pub fn from_url(url: &Url) -> Option<Origin> {
+        match url.scheme() {
+            "http" | "https" => {}
+            _ => return None,
+        }
+        Some(Origin {
+            scheme: url.scheme().to_string(),
+            host: url.host_str()?.to_string(),
+            port: url.port_or_known_default()?,
+        })
+    }
+
+    pub fn robots_txt_url(&self) -> Url {
+        format!("{}/robots.txt", self)
+            .parse()
+            .expect("origin makes a valid URL")
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}:{}", self.scheme, self.host, self.port)
+    }
+}
+
+/// A policy as it is kept in the cache.
+#[derive(Clone, Debug)]
+pub struct CachedPolicy {
+    /// For an unreachable host this may be the last copy fetched successfully.
+    pub matcher: Arc<RobotsMatcher>,
+    pub availability: Availability,
+    pub status: Option<u16>,
+    pub fetched_at: SystemTime,
+    pub expires_at: SystemTime,
+    pub validators: Validators,
+    /// When the host became unreachable, if it is.
+    pub unreachable_since: Option<SystemTime>,
+    /// When the robots.txt `matcher` holds was fetched successfully, if it was.
+    ///
+    /// An unreachable host keeps it until `UNREACHABLE_LIMIT`, across failed refreshes.
+    pub last_good_at: Option<SystemTime>,
+    /// Set if the body was not text, see `FetchResult::diagnostic`.
+    pub diagnostic: Option<PayloadDiagnostic>,
+    /// The hash of the robots.txt the policy was parsed from, see `FetchResult::source_hash`.
+    pub source_hash: Option<u64>,
+}
+
+impl CachedPolicy {
+    pub fn from_result(result: FetchResult, now: SystemTime, config: &CacheConfig) -> Self {
+        let ttl = match result.availability {
+            Availability::Unreachable => config.error_ttl,
+            _ => result
+                .find_header("cache-control")
+                .and_then(max_age)
+                .unwrap_or(config.ttl),
+        };
+        let validators = Validators {
+            etag: result.find_header("etag").map(String::from),
+            last_modified: result.find_header("last-modified").map(String::from),
+        };
+        CachedPolicy {
+            unreachable_since: match result.availability {
+                Availability::Unreachable => Some(now),
+                _ => None,
+            },
+            last_good_at: match result.availability {
+                Availability::Available => Some(now),
+                _ => None,
+            },
+            matcher: Arc::new(RobotsMatcher::new(result.robots)),
+            availability: result.availability,
+            status: result.status,
+            fetched_at: now,
+            expires_at: now + ttl,
+            validators,
+            diagnostic: result.diagnostic,
+            source_hash: result.source_hash,
+        }
+    }
+
+    pub fn is_fresh(&self, now: SystemTime) -> bool {
+        now < self.expires_at
+    }
+
+    pub fn check_path(&self, ua: &str, path: &str) -> bool {
+        self.matcher.check_path(ua, path)
+    }
+}
+
+/// The lifetime given by `Cache-Control`, zero for `no-cache` and `no-store`.
+pub fn max_age(cache_control: &str) -> Option<Duration> {
+    let mut max_age = None;
+    for directive in cache_control.split(',') {
+        let directive = directive.trim();
+        if directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+        {
+            return Some(Duration::from_secs(0));
+        }
+        let mut parts = directive.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("max-age") {
+            max_age = parts
+                .next()
+                .and_then(|value| value.trim().trim_matches('"').parse().ok())
+                .map(Duration::from_secs);
+        }
+    }
+    max_age
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheConfig {
+    /// The maximum number of origins kept, the least recently used ones are dropped first.
+    pub capacity: usize,
+    /// How long a policy is used when the response doesn't say, RFC 9309 suggests 24 hours.
+    pub ttl: Duration,
+    /// How long to wait before asking an unreachable host again.
+    pub error_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            capacity: 10_000,
+            ttl: Duration::from_secs(24 * 60 * 60),
+            error_ttl: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn new() -> Self {
+        CacheConfig::default()
+    }
+
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn error_ttl(mut self, ttl: Duration) -> Self {
+        self.error_ttl = ttl;
+        self
+    }
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<Origin, (u64, Arc<CachedPolicy>)>,
+    /// Origins by the tick of their last use.
+    lru: BTreeMap<u64, Origin>,
+    tick: u64,
+    in_flight: HashSet<Origin>,
+    /// Async lookups waiting for an origin in flight.
+    waiters: HashMap<Origin, Vec<Waker>>,
+}
+
+impl State {
+    fn touch(&mut self, origin: &Origin) -> Option<Arc<CachedPolicy>> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (used, entry) = self.entries.get_mut(origin)?;
+        self.lru.remove(used);
+        self.lru.insert(tick, origin.clone());
+        *used = tick;
+        Some(entry.clone())
+    }
+
+    fn insert(&mut self, origin: Origin, entry: Arc<CachedPolicy>, capacity: usize) {
+        self.tick += 1;
+        if let Some((used, _)) = self.entries.remove(&origin) {
+            self.lru.remove(&used);
+        }
+        while self.entries.len() >= capacity.max(1) {
+            let oldest = match self.lru.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(origin) = self.lru.remove(&oldest) {
+                self.entries.remove(&origin);
+            }
+        }
+        self.lru.insert(self.tick, origin.clone());
+        self.entries.insert(origin, (self.tick, entry));
+    }
+
+    fn remove(&mut self, origin: &Origin) -> Option<Arc<CachedPolicy>> {
+        let (used, entry) = self.entries.remove(origin)?;
+        self.lru.remove(&used);
+        Some(entry)
+    }
+}
+
+/// A shared cache of policies by origin.
+///
+/// Concurrent lookups of the same origin wait for a single fetch.
+/// With a [`PolicyStore`](../store/trait.PolicyStore.html) missing origins are looked up there
+/// before fetching, and every fetched policy is written through to it.
+///
+/// A cache over an [`AsyncRobotsFetcher`](../fetch/trait.AsyncRobotsFetcher.html) is used through
+/// `get_async` and `is_allowed_async`, which wait for a fetch in flight without blocking and
+/// use the store through the fetcher's `spawn_blocking`.
+pub struct PolicyCache<F> {
+    fetcher: F,
+    config: CacheConfig,
+    clock: Arc<dyn Clock>,
+    store: Option<Arc<dyn PolicyStore>>,
+    state: Mutex<State>,
+    fetched: Condvar,
+}
+
+impl<F> PolicyCache<F> {
+    pub fn new(fetcher: F) -> Self {
+        PolicyCache::with_config(fetcher, CacheConfig::default())
+    }
+
+    pub fn with_config(fetcher: F, config: CacheConfig) -> Self {
+        PolicyCache {
+            fetcher,
+            config,
+            clock: Arc::new(SystemClock),
+            store: None,
+            state: Mutex::new(State::default()),
+            fetched: Condvar::new(),
+        }
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keeps policies in the store too, so they survive restarts.
+    pub fn store(mut self, store: Arc<dyn PolicyStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// The cached policy, without fetching and regardless of its age.
+    pub fn peek(&self, origin: &Origin) -> Option<Arc<CachedPolicy>> {
+        self.lock()
+            .entries
+            .get(origin)
+            .map(|(_, entry)| entry.clone())
+    }
+
+    pub fn insert(&self, origin: Origin, entry: CachedPolicy) {
+        self.lock()
+            .insert(origin, Arc::new(entry), self.config.capacity)
+    }
+
+    /// Drops the policy from memory and from the store.
+    pub fn invalidate(&self, origin: &Origin) -> Option<Arc<CachedPolicy>> {
+        if let Some(store) = self.store.as_ref() {
+            store.remove(origin).ok();
+        }
+        self.lock().remove(origin)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A fresh entry, or the claim to fetch the origin with the stale entry if nobody else is.
+    fn lookup(&self, state: &mut State, origin: &Origin) -> Lookup {
+        if let Some(entry) = state.touch(origin) {
+            if entry.is_fresh(self.clock.now()) {
+                return Lookup::Fresh(entry);
+            }
+        }
+        if state.in_flight.contains(origin) {
+            return Lookup::InFlight;
+        }
+        state.in_flight.insert(origin.clone());
+        Lookup::Claimed(state.entries.get(origin).map(|(_, entry)| entry.clone()))
+    }
+
+    /// The entry if it is fresh, or else the stale one to refresh.
+    fn fresh(
+        &self,
+        entry: Option<Arc<CachedPolicy>>,
+    ) -> Result<Arc<CachedPolicy>, Option<Arc<CachedPolicy>>> {
+        match entry {
+            Some(entry) if entry.is_fresh(self.clock.now()) => Ok(entry),
+            stale => Err(stale),
+        }
+    }
+
+    fn load(&self, origin: &Origin) -> Option<CachedPolicy> {
+        load(self.store.as_deref()?, origin)
+    }
+
+    fn save(&self, origin: &Origin, entry: &CachedPolicy) {
+        if let Some(store) = self.store.as_deref() {
+            save(store, origin.clone(), entry);
+        }
+    }
+}
+
+impl<F> PolicyCache<F>
+where
+    F: RobotsFetcher,
+{
+    /// Checks a URL against the policy of its origin. URLs other than HTTP(S) are allowed.
+    pub fn is_allowed(&self, ua: &str, url: &Url) -> bool {
+        match self.get(url) {
+            Some(policy) => policy.check_path(ua, url_path(url)),
+            None => true,
+        }
+    }
+
+    /// The policy for the origin of this URL, fetched if it is missing or expired.
+    pub fn get(&self, url: &Url) -> Option<Arc<CachedPolicy>> {
+        let origin = Origin::from_url(url)?;
+        let mut state = self.lock();
+        let stale = loop {
+            match self.lookup(&mut state, &origin) {
+                Lookup::Fresh(entry) => return Some(entry),
+                Lookup::Claimed(stale) => break stale,
+                Lookup::InFlight => {
+                    state = self.fetched.wait(state).unwrap_or_else(|e| e.into_inner())
+                }
+            }
+        };
+        drop(state);
+
+        let guard = InFlight {
+            cache: self,
+            origin: &origin,
+        };
+        let stale = stale.or_else(|| self.load(&origin).map(Arc::new));
+        let entry = self.fresh(stale).unwrap_or_else(|stale| {
+            let entry = Arc::new(self.refresh(&origin, stale.as_deref()));
+            self.save(&origin, &entry);
+            entry
+        });
+        self.lock()
+            .insert(origin.clone(), entry.clone(), self.config.capacity);
+        drop(guard);
+        Some(entry)
+    }
+
+    fn refresh(&self, origin: &Origin, stale: Option<&CachedPolicy>) -> CachedPolicy {
+        let mut refresh = Refresh::new(origin, stale, self.fetcher.config(), self.clock.now());
+        let mut step = refresh.machine.start();
+        loop {
+            let url = match step {
+                Step::Fetch(url) => url,
+                Step::Done(result) => return refresh.finish(result, &self.config),
+            };
+            let outcome = self.fetcher.get_conditional(&url, &refresh.validators);
+            if let Some(entry) = refresh.revalidated(&outcome, &self.config) {
+                return entry;
+            }
+            step = refresh.machine.next(outcome);
+        }
+    }
+}
+
+impl<F> PolicyCache<F>
+where
+    F: AsyncRobotsFetcher,
+{
+    /// `is_allowed` over an async fetcher.
+    pub async fn is_allowed_async(&self, ua: &str, url: &Url) -> bool {
+        match self.get_async(url).await {
+            Some(policy) => policy.check_path(ua, url_path(url)),
+            None => true,
+        }
+    }
+
+    /// `get` over an async fetcher, waiting for a fetch in flight without blocking the thread.
+    pub async fn get_async(&self, url: &Url) -> Option<Arc<CachedPolicy>> {
+        let origin = Origin::from_url(url)?;
+        let stale = loop {
+            let lookup = self.lookup(&mut self.lock(), &origin);
+            match lookup {
+                Lookup::Fresh(entry) => return Some(entry),
+                Lookup::Claimed(stale) => break stale,
+                Lookup::InFlight => {
+                    Fetched {
+                        cache: self,
+                        origin: &origin,
+                    }
+                    .await
+                }
+            }
+        };
+
+        let guard = InFlight {
+            cache: self,
+            origin: &origin,
+        };
+        let stale = match stale {
+            Some(stale) => Some(stale),
+            None => self.load_async(&origin).await.map(Arc::new),
+        };
+        let entry = match self.fresh(stale) {
+            Ok(entry) => entry,
+            Err(stale) => {
+                let entry = Arc::new(self.refresh_async(&origin, stale.as_deref()).await);
+                self.save_async(&origin, entry.clone()).await;
+                entry
+            }
+        };
+        self.lock()
+            .insert(origin.clone(), entry.clone(), self.config.capacity);
+        drop(guard);
+        Some(entry)
+    }
+
+    /// `load` on the fetcher's blocking threads.
+    async fn load_async(&self, origin: &Origin) -> Option<CachedPolicy> {
+        let store = self.store.clone()?;
+        let origin = origin.clone();
+        self.blocking(move || load(&*store, &origin)).await?
+    }
+
+    /// `save` on the fetcher's blocking threads.
+    async fn save_async(&self, origin: &Origin, entry: Arc<CachedPolicy>) {
+        if let Some(store) = self.store.clone() {
+            let origin = origin.clone();
+            self.blocking(move || save(&*store, origin, &entry)).await;
+        }
+    }
+
+    /// The result of the work, `None` if it panicked.
+    async fn blocking<W, T>(&self, work: W) -> Option<T>
+    where
+        W: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let result = Arc::new(Mutex::new(None));
+        let slot = result.clone();
+        let task = Box::new(move || {
+            let value = work();
+            *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(value);
+        });
+        self.fetcher.spawn_blocking(task).await;
+        let value = result.lock().unwrap_or_else(|e| e.into_inner()).take();
+        value
+    }
+
+    async fn refresh_async(&self, origin: &Origin, stale: Option<&CachedPolicy>) -> CachedPolicy {
+        let mut refresh = Refresh::new(origin, stale, self.fetcher.config(), self.clock.now());
+        let mut step = refresh.machine.start();
+        loop {
+            let url = match step {
+                Step::Fetch(url) => url,
+                Step::Done(result) => return refresh.finish(result, &self.config),
+            };
+            let outcome = self
+                .fetcher
+                .get_conditional(&url, &refresh.validators)
+                .await;
+            if let Some(entry) = refresh.revalidated(&outcome, &self.config) {
+                return entry;
+            }
+            step = refresh.machine.next(outcome);
+        }
+    }
+}
+
+enum Lookup {
+    Fresh(Arc<CachedPolicy>),
+    /// Another caller is fetching the origin.
+    InFlight,
+    /// The caller fetches the origin, replacing the stale entry if there is one.
+    Claimed(Option<Arc<CachedPolicy>>),
+}
+
+/// The fetch of a policy, whichever way its requests are made.
+struct Refresh<'s> {
+    stale: Option<&'s CachedPolicy>,
+    now: SystemTime,
+    validators: Validators,
+    unreachable_since: Option<SystemTime>,
+    unreachable_for: Duration,
+    machine: FetchMachine,
+}
+
+impl<'s> Refresh<'s> {
+    fn new(
+        origin: &Origin,
+        stale: Option<&'s CachedPolicy>,
+        config: &FetchConfig,
+        now: SystemTime,
+    ) -> Self {
+        let validators = match stale {
+            Some(stale) if stale.availability == Availability::Available => {
+                stale.validators.clone()
+            }
+            _ => Validators::default(),
+        };
+        let unreachable_since = stale.and_then(|stale| stale.unreachable_since);
+        let unreachable_for = unreachable_since
+            .and_then(|since| now.duration_since(since).ok())
+            .unwrap_or_default();
+        let machine = FetchMachine::new(origin.robots_txt_url())
+            .max_redirects(config.max_redirects)
+            .payload_handling(config.payload_handling)
+            .unreachable_for(unreachable_for);
+        Refresh {
+            stale,
+            now,
+            validators,
+            unreachable_since,
+            unreachable_for,
+            machine,
+        }
+    }
+
+    /// The stale entry renewed, if the response says it is still current.
+    fn revalidated(&self, outcome: &FetchOutcome, config: &CacheConfig) -> Option<CachedPolicy> {
+        match (outcome, self.stale) {
+            (FetchOutcome::Response(response), Some(stale))
+                if response.status == 304 && !self.validators.is_empty() =>
+            {
+                let ttl = response
+                    .find_header("cache-control")
+                    .and_then(max_age)
+                    .unwrap_or(config.ttl);
+                Some(CachedPolicy {
+                    fetched_at: self.now,
+                    expires_at: self.now + ttl,
+                    last_good_at: Some(self.now),
+                    ..stale.clone()
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn finish(self, result: FetchResult, config: &CacheConfig) -> CachedPolicy {
+        let mut entry = CachedPolicy::from_result(result, self.now, config);
+        if entry.availability == Availability::Unreachable {
+            entry.unreachable_since = Some(self.unreachable_since.unwrap_or(self.now));
+            // An unreachable host may be crawled by the last copy of its robots.txt.
+            if let Some(stale) = self.stale {
+                if stale.last_good_at.is_some() && self.unreachable_for < UNREACHABLE_LIMIT {
+                    entry.matcher = stale.matcher.clone();
+                    entry.last_good_at = stale.last_good_at;
+                    entry.validators = stale.validators.clone();
+                    entry.diagnostic = stale.diagnostic;
+                    entry.source_hash = stale.source_hash;
+                }
+            }
+        }
+        entry
+    }
+}
+
+/// Marks the end of a fetch even if it panicked or its future was dropped.
+struct InFlight<'c, F> {
+    cache: &'c PolicyCache<F>,
+    origin: &'c Origin,
+}
+
+impl<'c, F> Drop for InFlight<'c, F> {
+    fn drop(&mut self) {
+        let mut state = self.cache.lock();
+        state.in_flight.remove(self.origin);
+        for waker in state.waiters.remove(self.origin).unwrap_or_default() {
+            waker.wake();
+        }
+        self.cache.fetched.notify_all();
+    }
+}
+
+/// Waits for the fetch of an origin by another caller to end.
+struct Fetched<'c, F> {
+    cache: &'c PolicyCache<F>,
+    origin: &'c Origin,
+}
+
+impl<'c, F> Future for Fetched<'c, F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.cache.lock();
+        match state.in_flight.contains(self.origin) {
+            true => {
+                state
+                    .waiters
+                    .entry(self.origin.clone())
+                    .or_default()
+                    .push(cx.waker().clone());
+                Poll::Pending
+            }
+            false => Poll::Ready(()),
+        }
+    }
+}
+
+/// A store which fails is treated as empty, the cache keeps working from the network.
+fn load(store: &dyn PolicyStore, origin: &Origin) -> Option<CachedPolicy> {
+    Some(store.load(origin).ok()??.to_cached())
+}
+
+fn save(store: &dyn PolicyStore, origin: Origin, entry: &CachedPolicy) {
+    store.save(&StoredPolicy::from_cached(origin, entry)).ok();
+}
+
+/// The path with the query, which is what rules are matched against.
+fn url_path(url: &Url) -> &str {
+    &url[Position::BeforePath..Position::AfterQuery]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread::{self, ThreadId},
+    };
+
+    use super::*;
+    use crate::{clock::ManualClock, payload::PayloadKind, store::FileStore};
+
+    type Reply = Box<dyn Fn(usize, &Validators) -> FetchOutcome + Send + Sync>;
+
+    struct MockFetcher {
+        config: FetchConfig,
+        calls: AtomicUsize,
+        conditional: Mutex<Vec<Validators>>,
+        reply: Reply,
+    }
+
+    impl MockFetcher {
+        fn new<R>(reply: R) -> Self
+        where
+            R: Fn(usize, &Validators) -> FetchOutcome + Send + Sync + 'static,
+        {
+            MockFetcher {
+                config: FetchConfig::default(),
+                calls: AtomicUsize::new(0),
+                conditional: Mutex::new(Vec::new()),
+                reply: Box::new(reply),
+            }
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl RobotsFetcher for MockFetcher {
+        fn config(&self) -> &FetchConfig {
+            &self.config
+        }
+
+        fn get(&self, url: &Url) -> FetchOutcome {
+            self.get_conditional(url, &Validators::default())
+        }
+
+        fn get_conditional(&self, _: &Url, validators: &Validators) -> FetchOutcome {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            self.conditional.lock().unwrap().push(validators.clone());
+            (self.reply)(call, validators)
+        }
+    }
+
+    /// The mock over an async interface, each request yielding once.
+    struct AsyncMockFetcher(MockFetcher);
+
+    impl AsyncRobotsFetcher for AsyncMockFetcher {
+        fn config(&self) -> &FetchConfig {
+            &self.0.config
+        }
+
+        fn get<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, FetchOutcome> {
+            Box::pin(async move { self.get_conditional(url, &Validators::default()).await })
+        }
+
+        fn get_conditional<'a>(
+            &'a self,
+            url: &'a Url,
+            validators: &'a Validators,
+        ) -> BoxFuture<'a, FetchOutcome> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                RobotsFetcher::get_conditional(&self.0, url, validators)
+            })
+        }
+    }
+
+    fn ok(body: &str) -> FetchOutcome {
+        FetchOutcome::Response(Response::new(200).body(body))
+    }
+
+    fn url(s: &str) -> Url {
+        s.parse().unwrap()
+    }
+
+    fn new_cache<R>(reply: R) -> (PolicyCache<MockFetcher>, Arc<ManualClock>)
+    where
+        R: Fn(usize, &Validators) -> FetchOutcome + Send + Sync + 'static,
+    {
+        let clock = Arc::new(ManualClock::default());
+        let cache = PolicyCache::new(MockFetcher::new(reply)).clock(clock.clone());
+        (cache, clock)
+    }
+
+    #[test]
+    fn origin() {
+        let origin = Origin::from_url(&url("https://example.com/a?b")).unwrap();
+        assert_eq!("https://example.com:443", origin.to_string());
+        assert_eq!(
+            "https://example.com/robots.txt",
+            origin.robots_txt_url().as_str()
+        );
+        assert_ne!(
+            origin,
+            Origin::from_url(&url("http://example.com/")).unwrap()
+        );
+        assert!(Origin::from_url(&url("data:text/plain,robots")).is_none());
+    }
+
+    #[test]
+    fn cache_control() {
+        assert_eq!(Some(Duration::from_secs(60)), max_age("public, max-age=60"));
+        assert_eq!(Some(Duration::from_secs(0)), max_age("no-cache"));
+        assert_eq!(None, max_age("public"));
+    }
+
+    #[test]
+    fn ttl() {
+        let (cache, clock) = new_cache(|_, _| ok("User-agent: *\nDisallow: /private\n"));
+        assert!(!cache.is_allowed("bot", &url("https://example.com/private?x")));
+        assert!(cache.is_allowed("bot", &url("https://example.com/public")));
+        assert!(cache.is_allowed("bot", &url("https://example.com:443/public")));
+        assert_eq!(1, cache.fetcher.calls());
+
+        clock.advance(Duration::from_secs(24 * 60 * 60 - 1));
+        cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(1, cache.fetcher.calls());
+        clock.advance(Duration::from_secs(1));
+        cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(2, cache.fetcher.calls());
+    }
+
+    #[test]
+    fn revalidation() {
+        let (cache, clock) = new_cache(|call, validators| match call {
+            0 => FetchOutcome::Response(
+                Response::new(200)
+                    .with_header("ETag", "\"v1\"")
+                    .with_header("Cache-Control", "max-age=60")
+                    .body("User-agent: *\nDisallow: /\n"),
+            ),
+            _ if validators.etag.as_deref() == Some("\"v1\"") => {
+                FetchOutcome::Response(Response::new(304))
+            }
+            _ => ok(""),
+        });
+        let policy = cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(
+            Duration::from_secs(60),
+            policy.expires_at.duration_since(policy.fetched_at).unwrap()
+        );
+
+        clock.advance(Duration::from_secs(61));
+        let revalidated = cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(2, cache.fetcher.calls());
+        assert!(Arc::ptr_eq(&policy.matcher, &revalidated.matcher));
+        assert!(revalidated.is_fresh(clock.now()));
+        assert!(!revalidated.check_path("bot", "/"));
+        assert_eq!(
+            Some("\"v1\""),
+            cache.fetcher.conditional.lock().unwrap()[1].etag.as_deref()
+        );
+    }
+
+    #[test]
+    fn unsolicited_not_modified() {
+        let (cache, clock) = new_cache(|call, _| match call {
+            0 => FetchOutcome::Response(Response::new(304)),
+            1 => ok("User-agent: *\nDisallow: /private\n"),
+            _ => FetchOutcome::Response(Response::new(304)),
+        });
+        let policy = cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(Availability::Unreachable, policy.availability);
+        assert!(!policy.check_path("bot", "/public"));
+
+        // Without validators to send the last good copy stays in use.
+        clock.advance(cache.config().error_ttl);
+        cache.get(&url("https://example.com/")).unwrap();
+        clock.advance(cache.config().ttl);
+        let policy = cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(Availability::Unreachable, policy.availability);
+        assert!(policy.check_path("bot", "/public"));
+        assert!(!policy.check_path("bot", "/private"));
+    }
+
+    #[test]
+    fn unreachable_keeps_last_copy() {
+        let (cache, clock) = new_cache(|call, _| match call {
+            0 => ok("User-agent: *\nDisallow: /private\n"),
+            _ => FetchOutcome::Response(Response::new(503)),
+        });
+        cache.get(&url("http://example.com/")).unwrap();
+        clock.advance(Duration::from_secs(25 * 60 * 60));
+        let policy = cache.get(&url("http://example.com/")).unwrap();
+        assert_eq!(Availability::Unreachable, policy.availability);
+        assert_eq!(Some(clock.now()), policy.unreachable_since);
+        assert!(policy.check_path("bot", "/public"));
+        assert!(!policy.check_path("bot", "/private"));
+
+        // Failing again after the error TTL still keeps it.
+        let since = policy.unreachable_since;
+        clock.advance(cache.config().error_ttl);
+        let policy = cache.get(&url("http://example.com/")).unwrap();
+        assert_eq!(3, cache.fetcher.calls());
+        assert_eq!(since, policy.unreachable_since);
+        assert!(policy.check_path("bot", "/public"));
+        assert!(!policy.check_path("bot", "/private"));
+
+        let (cache, _) = new_cache(|_, _| FetchOutcome::NetworkError("timeout".into()));
+        let policy = cache.get(&url("http://example.com/")).unwrap();
+        assert!(!policy.check_path("bot", "/public"));
+    }
+
+    #[test]
+    fn diagnostic() {
+        let (cache, _) = new_cache(|_, _| {
+            FetchOutcome::Response(
+                Response::new(200)
+                    .with_header("Content-Type", "text/html")
+                    .body("Service temporarily unavailable"),
+            )
+        });
+        let policy = cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(
+            Some(PayloadKind::Html),
+            policy.diagnostic.map(|diagnostic| diagnostic.kind)
+        );
+        assert!(policy.check_path("bot", "/"));
+    }
+
+    #[test]
+    fn lru() {
+        let clock = Arc::new(ManualClock::default());
+        let cache = PolicyCache::with_config(
+            MockFetcher::new(|_, _| ok("")),
+            CacheConfig::new().capacity(2),
+        )
+        .clock(clock);
+        let origin = |host: &str| Origin::from_url(&url(&format!("https://{}/", host))).unwrap();
+
+        cache.get(&url("https://a.com/")).unwrap();
+        cache.get(&url("https://b.com/")).unwrap();
+        cache.get(&url("https://a.com/")).unwrap();
+        cache.get(&url("https://c.com/")).unwrap();
+        assert_eq!(2, cache.len());
+        assert!(cache.peek(&origin("a.com")).is_some());
+        assert!(cache.peek(&origin("b.com")).is_none());
+        assert!(cache.peek(&origin("c.com")).is_some());
+        assert_eq!(3, cache.fetcher.calls());
+
+        assert!(cache.invalidate(&origin("a.com")).is_some());
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn store() {
+        let dir = crate::store::tests::temp_dir("cache");
+        let store: Arc<dyn PolicyStore> = Arc::new(FileStore::open(&dir).unwrap());
+        let clock = Arc::new(ManualClock::default());
+        let open = |clock: &Arc<ManualClock>| {
+            PolicyCache::new(MockFetcher::new(|_, _| {
+                FetchOutcome::Response(
+                    Response::new(200)
+                        .with_header("ETag", "\"v1\"")
+                        .body("User-agent: *\nDisallow: /private\n"),
+                )
+            }))
+            .clock(clock.clone())
+            .store(store.clone())
+        };
+
+        let cache = open(&clock);
+        assert!(!cache.is_allowed("bot", &url("https://example.com/private")));
+        assert_eq!(1, cache.fetcher.calls());
+
+        // A restarted crawler finds the policy on disk.
+        let cache = open(&clock);
+        assert!(!cache.is_allowed("bot", &url("https://example.com/private")));
+        assert!(cache.is_allowed("bot", &url("https://example.com/public")));
+        assert_eq!(0, cache.fetcher.calls());
+
+        // An expired one is revalidated with the stored validators.
+        clock.advance(Duration::from_secs(24 * 60 * 60));
+        let cache = open(&clock);
+        cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(1, cache.fetcher.calls());
+        assert_eq!(
+            Some("\"v1\""),
+            cache.fetcher.conditional.lock().unwrap()[0].etag.as_deref()
+        );
+
+        cache.invalidate(&Origin::from_url(&url("https://example.com/")).unwrap());
+        let cache = open(&clock);
+        cache.get(&url("https://example.com/")).unwrap();
+        assert_eq!(1, cache.fetcher.calls());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn single_flight() {
+        let cache = Arc::new(PolicyCache::new(MockFetcher::new(|_, _| {
+            thread::sleep(Duration::from_millis(100));
+            ok("User-agent: *\nDisallow: /\n")
+        })));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                thread::spawn(move || cache.is_allowed("bot", &url("https://example.com/")))
+            })
+            .collect();
+        for thread in threads {
+            assert!(!thread.join().unwrap());
+        }
+        assert_eq!(1, cache.fetcher.calls());
+    }
+
+    #[tokio::test]
+    async fn single_flight_async() {
+        fn send<T: Send>(future: T) -> T {
+            future
+        }
+        let clock = Arc::new(ManualClock::default());
+        let cache = PolicyCache::new(AsyncMockFetcher(MockFetcher::new(|_, _| {
+            ok("User-agent: *\nDisallow: /private\n")
+        })))
+        .clock(clock.clone());
+        let (private, public) = (
+            url("https://example.com/private"),
+            url("https://example.com/"),
+        );
+        let (a, b, policy) = tokio::join!(
+            cache.is_allowed_async("bot", &private),
+            cache.is_allowed_async("bot", &public),
+            send(cache.get_async(&public)),
+        );
+        assert!(!a && b);
+        assert!(!policy.unwrap().check_path("bot", "/private"));
+        assert_eq!(1, cache.fetcher.0.calls());
+
+        clock.advance(Duration::from_secs(24 * 60 * 60));
+        assert!(cache.is_allowed_async("bot", &public).await);
+        assert_eq!(2, cache.fetcher.0.calls());
+    }
+
+    /// A store which records the threads it is used on.
+    struct ThreadStore(FileStore, Mutex<Vec<ThreadId>>);
+
+    impl ThreadStore {
+        fn used(&self) {
+            self.1.lock().unwrap().push(thread::current().id());
+        }
+    }
+
+    impl PolicyStore for ThreadStore {
+        fn load(&self, origin: &Origin) -> io::Result<Option<StoredPolicy>> {
+            self.used();
+            self.0.load(origin)
+        }
+
+        fn save(&self, policy: &StoredPolicy) -> io::Result<()> {
+            self.used();
+            self.0.save(policy)
+        }
+
+        fn remove(&self, origin: &Origin) -> io::Result<()> {
+            self.used();
+            self.0.remove(origin)
+        }
+    }
+
+    #[tokio::test]
+    async fn store_async() {
+        let dir = crate::store::tests::temp_dir("cache-async");
+        let store = Arc::new(ThreadStore(
+            FileStore::open(&dir).unwrap(),
+            Mutex::default(),
+        ));
+        let open = || {
+            PolicyCache::new(AsyncMockFetcher(MockFetcher::new(|_, _| {
+                ok("User-agent: *\nDisallow: /private\n")
+            })))
+            .store(store.clone())
+        };
+        let private = url("https://example.com/private");
+
+        let cache = open();
+        assert!(!cache.is_allowed_async("bot", &private).await);
+        let cache = open();
+        assert!(!cache.is_allowed_async("bot", &private).await);
+        assert_eq!(0, cache.fetcher.0.calls());
+
+        // Loaded, saved and loaded again, never on the thread running the futures.
+        let threads = store.1.lock().unwrap().clone();
+        assert_eq!(3, threads.len());
+        assert!(!threads.contains(&thread::current().id()));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,568 @@
+//! Assembling one robots.txt from fragments owned by different teams.
+//!
+//! Each fragment is a robots.txt of its own with `Scope:` lines naming the path prefixes
+//! it may write rules for:
+//!
+//! ```text
+//! Scope: /shop/
+//!
+//! User-agent: *
+//! Disallow: /shop/cart
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt,
+    iter::FromIterator,
+};
+
+use unicase::UniCase;
+
+use crate::{builder::*, parse::*, parts::*};
+
+/// A part of a robots.txt and the paths it is responsible for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fragment<'a> {
+    pub name: Cow<'a, str>,
+    /// Prefixes the rules of the fragment must stay within.
+    pub scopes: Vec<PathPattern<'a>>,
+    pub robots: Robots<'a>,
+}
+
+impl<'a> Fragment<'a> {
+    pub fn new<N>(name: N, robots: Robots<'a>) -> Self
+    where
+        N: Into<Cow<'a, str>>,
+    {
+        Fragment {
+            name: name.into(),
+            scopes: Vec::new(),
+            robots,
+        }
+    }
+
+    /// Reads a fragment with its `Scope:` lines.
+    pub fn parse<N>(name: N, input: &'a str) -> Self
+    where
+        N: Into<Cow<'a, str>>,
+    {
+        let mut fragment = Fragment::new(name, Robots::from_str_lossy(input));
+        for line in input.lines() {
+            if let Some((k, v)) = split_kv(split_comment(line).0) {
+                if UniCase::new(k) == UniCase::new("scope") {
+                    fragment.scopes.push(PathPattern::new(v));
+                }
+            }
+        }
+        fragment
+    }
+
+    pub fn scope<P>(mut self, prefix: P) -> Self
+    where
+        P: Into<PathPattern<'a>>,
+    {
+        self.scopes.push(prefix.into());
+        self
+    }
+
+    /// Whether the rule only matches paths within the scopes.
+    pub fn is_in_scope(&self, rule: &Rule<'_>) -> bool {
+        rule.path.is_empty() || self.scopes.iter().any(|scope| scope.subsumes(&rule.path))
+    }
+
+    /// The groups the fragment writes, `*` last.
+    fn groups(&self) -> impl Iterator<Item = &Section<'a>> {
+        let default_section = &self.robots.default_section;
+        let written = *default_section != Section::default();
+        self.robots
+            .sections
+            .iter()
+            .chain(Some(default_section).filter(|_| written))
+    }
+}
+
+/// A problem found while composing fragments.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComposeIssue {
+    /// A rule outside the scopes of its fragment.
+    OutOfScope {
+        fragment: String,
+        agent: String,
+        rule: String,
+    },
+    /// Rules of two fragments with opposite verdicts for the same agent and path.
+    Contradiction {
+        agent: String,
+        allow: (String, String),
+        disallow: (String, String),
+        /// A path both rules match.
+        path: String,
+    },
+    /// Two fragments set a directive differently for the same agent.
+    Setting {
+        agent: String,
+        directive: &'static str,
+        fragments: (String, String),
+    },
+    /// Two fragments set different `Host`s.
+    Host { fragments: (String, String) },
+}
+
+impl fmt::Display for ComposeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComposeIssue::OutOfScope {
+                fragment,
+                agent,
+                rule,
+            } => write!(
+                f,
+                "fragment `{}`: `{}` for `{}` is outside its scope",
+                fragment, rule, agent
+            ),
+            ComposeIssue::Contradiction {
+                agent,
+                allow,
+                disallow,
+                path,
+            } => write!(
+                f,
+                "fragments `{}` and `{}` contradict for `{}`: `Allow: {}` and `Disallow: {}` both match `{}`",
+                allow.0, disallow.0, agent, allow.1, disallow.1, path
+            ),
+            ComposeIssue::Setting {
+                agent,
+                directive,
+                fragments,
+            } => write!(
+                f,
+                "fragments `{}` and `{}` set different `{}` for `{}`",
+                fragments.0, fragments.1, directive, agent
+            ),
+            ComposeIssue::Host { fragments } => write!(
+                f,
+                "fragments `{}` and `{}` set different `Host`",
+                fragments.0, fragments.1
+            ),
+        }
+    }
+}
+
+impl Error for ComposeIssue {}
+
+/// Collects fragments and assembles them in an order which doesn't depend on the order
+/// they were added in.
+#[derive(Clone, Debug, Default)]
+pub struct Composer<'a> {
+    fragments: Vec<Fragment<'a>>,
+}
+
+impl<'a> Composer<'a> {
+    pub fn new() -> Self {
+        Composer::default()
+    }
+
+    pub fn fragment(mut self, fragment: Fragment<'a>) -> Self {
+        let position = self
+            .fragments
+            .iter()
+            .position(|other| other.name > fragment.name)
+            .unwrap_or(self.fragments.len());
+        self.fragments.insert(position, fragment);
+        self
+    }
+
+    pub fn fragments(&self) -> &[Fragment<'a>] {
+        &self.fragments
+    }
+
+    /// Rules outside their scopes and disagreements between fragments.
+    pub fn issues(&self) -> Vec<ComposeIssue> {
+        let mut issues = Vec::new();
+        for fragment in &self.fragments {
+            for section in fragment.groups() {
+                for rule in section.rules.iter() {
+                    if !fragment.is_in_scope(rule) {
+                        issues.push(ComposeIssue::OutOfScope {
+                            fragment: fragment.name.to_string(),
+                            agent: agent_name(section),
+                            rule: rule.to_string().trim_end().to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let groups: Vec<(&Fragment<'a>, &Section<'a>)> = self
+            .fragments
+            .iter()
+            .flat_map(|fragment| fragment.groups().map(move |section| (fragment, section)))
+            .collect();
+        for (i, &(a, first)) in groups.iter().enumerate() {
+            for &(b, second) in &groups[i + 1..] {
+                if a.name == b.name {
+                    continue;
+                }
+                let agent = match first
+                    .useragents
+                    .iter()
+                    .find(|ua| second.useragents.iter().any(|ua2| same_agent(ua, ua2)))
+                {
+                    Some(agent) => agent.to_string(),
+                    None => continue,
+                };
+                let names = (a.name.to_string(), b.name.to_string());
+                issues.extend(contradictions(
+                    &agent,
+                    (&names.0, &first.rules),
+                    (&names.1, &second.rules),
+                ));
+                let settings = [
+                    ("Crawl-delay", differ(first.crawl_delay, second.crawl_delay)),
+                    ("Request-rate", differ(first.req_rate, second.req_rate)),
+                    ("Visit-time", differ(first.visit_time, second.visit_time)),
+                ];
+                for &(directive, differs) in &settings {
+                    if differs {
+                        issues.push(ComposeIssue::Setting {
+                            agent: agent.clone(),
+                            directive,
+                            fragments: names.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let hosts: Vec<_> = self
+            .fragments
+            .iter()
+            .filter_map(|fragment| fragment.robots.host.as_ref().map(|host| (fragment, host)))
+            .collect();
+        for (i, (a, host)) in hosts.iter().enumerate() {
+            if let Some((b, _)) = hosts[i + 1..].iter().find(|(_, other)| other != host) {
+                issues.push(ComposeIssue::Host {
+                    fragments: (a.name.to_string(), b.name.to_string()),
+                });
+            }
+        }
+        issues
+    }
+
+    /// The fragments' groups merged per user agent, ordered by the user agents.
+    ///
+    /// A group for several agents is split, so every agent gets the rules of all the groups
+    /// naming it, and agents ending up with the same rules share a group again.
+    /// Rules keep the order of the fragments' names, rules repeated by another fragment are
+    /// left out. Sitemaps go to the `*` group, the first `Host` wins.
+    pub fn build(&self) -> Robots<'a> {
+        let mut agents: BTreeMap<String, Section<'a>> = BTreeMap::new();
+        let mut sitemaps = BTreeSet::new();
+        for fragment in &self.fragments {
+            for section in fragment.groups() {
+                let mut section = section.clone();
+                section.rules.retain(|rule| !rule.path.is_empty());
+                sitemaps.append(&mut section.sitemaps);
+                for ua in &section.useragents {
+                    let mut section = section.clone();
+                    section.useragents = BTreeSet::from_iter(Some(ua.clone()));
+                    match agents.get_mut(&ua.to_lowercase()) {
+                        Some(target) => merge(target, section),
+                        None => {
+                            agents.insert(ua.to_lowercase(), section);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<Section<'a>> = Vec::new();
+        for (_, section) in agents {
+            match groups.iter_mut().find(|group| {
+                !group.is_default() && !section.is_default() && same_rules(group, &section)
+            }) {
+                Some(group) => group.useragents.extend(section.useragents),
+                None => groups.push(section),
+            }
+        }
+
+        let mut builder = RobotsBuilder::new();
+        if let Some(host) = self
+            .fragments
+            .iter()
+            .find_map(|fragment| fragment.robots.host.clone())
+        {
+            builder = builder.host(host);
+        }
+        for mut section in groups {
+            if section.rules.is_empty() {
+                section.rules.push(Rule::disallow(""));
+            }
+            builder = builder.section(section);
+        }
+        let mut robots = builder.build();
+        robots.default_section.sitemaps.append(&mut sitemaps);
+        robots
+    }
+
+    /// Like `build`, but fails with all the issues found.
+    pub fn try_build(&self) -> Result<Robots<'a>, Vec<ComposeIssue>> {
+        let issues = self.issues();
+        match issues.is_empty() {
+            true => Ok(self.build()),
+            false => Err(issues),
+        }
+    }
+}
+
+/// Whether the groups are the same but for their user agents.
+fn same_rules(a: &Section<'_>, b: &Section<'_>) -> bool {
+    a.rules == b.rules
+        && a.usage == b.usage
+        && a.crawl_delay == b.crawl_delay
+        && a.req_rate == b.req_rate
+        && a.visit_time == b.visit_time
+        && a.comments == b.comments
+}
+
+/// Merges a group into another, leaving out rules it already has.
+fn merge<'a>(target: &mut Section<'a>, mut section: Section<'a>) {
+    section.rules.retain(|rule| {
+        !target
+            .rules
+            .iter()
+            .any(|other| other.allow == rule.allow && other.path == rule.path)
+    });
+    section.useragents = target.useragents.clone();
+    target.merge(section);
+}
+
+fn contradictions(
+    agent: &str,
+    (first, first_rules): (&str, &[Rule<'_>]),
+    (second, second_rules): (&str, &[Rule<'_>]),
+) -> Vec<ComposeIssue> {
+    let mut issues = Vec::new();
+    for a in first_rules.iter().filter(|rule| !rule.path.is_empty()) {
+        for b in second_rules
+            .iter()
+            .filter(|rule| rule.allow != a.allow && !rule.path.is_empty())
+        {
+            let path = match a.path.example_matched_by_both(&b.path) {
+                Some(path) => path,
+                None => continue,
+            };
+            let (allow, disallow) = match a.allow {
+                true => ((first, a), (second, b)),
+                false => ((second, b), (first, a)),
+            };
+            issues.push(ComposeIssue::Contradiction {
+                agent: agent.to_string(),
+                allow: (allow.0.to_string(), allow.1.path.to_string()),
+                disallow: (disallow.0.to_string(), disallow.1.path.to_string()),
+                path,
+            });
+        }
+    }
+    issues
+}
+
+fn differ<T>(a: Option<T>, b: Option<T>) -> bool
+where
+    T: PartialEq,
+{
+    match (a, b) {
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    }
+}
+
+fn same_agent(a: &str, b: &str) -> bool {
+    UniCase::new(a) == UniCase::new(b)
+}
+
+fn agent_name(section: &Section<'_>) -> String {
+    section
+        .useragents
+        .iter()
+        .map(|ua| ua.as_ref())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SHOP: &str = r#"
+Scope: /shop/
+
+User-agent: *
+Disallow: /shop/cart
+Allow: /shop/cart/share
+
+User-agent: Googlebot
+Disallow: /shop/search
+"#;
+
+    static DOCS: &str = r#"
+Scope: /docs/
+Scope: /api/
+
+User-agent: googlebot
+Disallow: /docs/drafts/
+
+User-agent: *
+Disallow: /docs/drafts/
+Sitemap: http://example.com/docs.xml
+"#;
+
+    #[test]
+    fn build() {
+        let robots = Composer::new()
+            .fragment(Fragment::parse("shop", SHOP))
+            .fragment(Fragment::parse("docs", DOCS))
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            robots.to_string(),
+            "\
+User-agent: googlebot
+Disallow: /docs/drafts/
+Disallow: /shop/search
+
+User-agent: *
+Disallow: /docs/drafts/
+Disallow: /shop/cart
+Allow: /shop/cart/share
+Sitemap: http://example.com/docs.xml
+
+"
+        );
+
+        // The order of the fragments makes no difference.
+        let reversed = Composer::new()
+            .fragment(Fragment::parse("docs", DOCS))
+            .fragment(Fragment::parse("shop", SHOP))
+            .build();
+        assert_eq!(robots, reversed);
+    }
+
+    #[test]
+    fn issues() {
+        let shop = SHOP.replace("share\n", "share\nCrawl-delay: 10\n") + "Host: www.example.com\n";
+        let marketing = "\
+Scope: /shop/
+
+User-agent: *
+Allow: /shop/cart/promo
+Disallow: /landing
+Crawl-delay: 5
+
+Host: example.com
+";
+        let composer = Composer::new()
+            .fragment(Fragment::new("shop", Robots::from_str_lossy(&shop)).scope("/shop/"))
+            .fragment(Fragment::parse("marketing", marketing))
+            .fragment(Fragment::parse("docs", DOCS));
+        let issues: Vec<String> = composer.issues().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "fragment `marketing`: `Disallow: /landing` for `*` is outside its scope",
+                "fragments `marketing` and `shop` contradict for `*`: \
+                 `Allow: /shop/cart/promo` and `Disallow: /shop/cart` both match `/shop/cart/promo`",
+                "fragments `marketing` and `shop` set different `Crawl-delay` for `*`",
+                "fragments `marketing` and `shop` set different `Host`",
+            ],
+            issues
+        );
+        assert_eq!(4, composer.try_build().unwrap_err().len());
+    }
+
+    #[test]
+    fn overlapping_agents() {
+        let shop = "\
+Scope: /shop/
+
+User-agent: googlebot
+User-agent: bingbot
+Disallow: /shop/cart
+";
+        let blog = "\
+Scope: /blog/
+
+User-agent: googlebot
+Disallow: /blog/drafts
+";
+        let composer = Composer::new()
+            .fragment(Fragment::parse("shop", shop))
+            .fragment(Fragment::parse("blog", blog));
+        assert_eq!(Vec::<ComposeIssue>::new(), composer.issues());
+        let robots = composer.build();
+        assert_eq!(
+            robots.to_string(),
+            "\
+User-agent: bingbot
+Disallow: /shop/cart
+
+User-agent: googlebot
+Disallow: /blog/drafts
+Disallow: /shop/cart
+
+User-agent: *
+Disallow:
+
+"
+        );
+        let matcher = crate::matcher::RobotsMatcher::new(robots.into_owned());
+        assert!(!matcher.check_path("googlebot", "/blog/drafts/x"));
+        assert!(!matcher.check_path("googlebot", "/shop/cart"));
+        assert!(matcher.check_path("bingbot", "/blog/drafts/x"));
+
+        // Agents with the same rules share a group again.
+        let blog = blog.replace("googlebot", "Bingbot\nUser-agent: googlebot");
+        let robots = Composer::new()
+            .fragment(Fragment::parse("shop", shop))
+            .fragment(Fragment::parse("blog", &blog))
+            .build();
+        assert_eq!(1, robots.sections.len());
+        assert_eq!(2, robots.sections[0].useragents.len());
+        assert_eq!(2, robots.sections[0].rules.len());
+    }
+
+    #[test]
+    fn empty_rules() {
+        let open = "\
+Scope: /
+
+User-agent: *
+Allow:
+";
+        let closed = "\
+Scope: /private/
+
+User-agent: *
+Allow:
+Disallow: /private/
+";
+        let composer = Composer::new()
+            .fragment(Fragment::parse("open", open))
+            .fragment(Fragment::parse("closed", closed));
+        assert_eq!(Vec::<ComposeIssue>::new(), composer.issues());
+        let robots = composer.build();
+        assert_eq!(robots.to_string(), "User-agent: *\nDisallow: /private/\n\n");
+    }
+
+    #[test]
+    fn scopes() {
+        let fragment = Fragment::parse("docs", DOCS);
+        assert_eq!(2, fragment.scopes.len());
+        assert!(fragment.is_in_scope(&Rule::disallow("/docs/drafts/")));
+        assert!(fragment.is_in_scope(&Rule::disallow("/api/*/internal")));
+        assert!(fragment.is_in_scope(&Rule::disallow("")));
+        assert!(!fragment.is_in_scope(&Rule::disallow("/")));
+        assert!(!fragment.is_in_scope(&Rule::disallow("/*.pdf")));
+    }
+}
@@ -0,0 +1,78 @@
+use std::{error::Error, fmt};
+
+/// A problem in a configuration file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    /// The line in the file, 1-based, if it is known.
+    pub line: Option<usize>,
+    /// Where in the configuration, e.g. `groups[0].disallow[1]`, empty for syntax errors.
+    pub location: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub(crate) fn new<L, M>(location: L, message: M) -> Self
+    where
+        L: Into<String>,
+        M: ToString,
+    {
+        ConfigError {
+            line: None,
+            location: location.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if !self.location.is_empty() {
+            write!(f, "{}: ", self.location)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ConfigError {}
+
+/// All the problems found in a configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl ConfigErrors {
+    pub fn errors(&self) -> &[ConfigError] {
+        &self.0
+    }
+}
+
+impl From<ConfigError> for ConfigErrors {
+    fn from(error: ConfigError) -> Self {
+        ConfigErrors(vec![error])
+    }
+}
+
+impl IntoIterator for ConfigErrors {
+    type Item = ConfigError;
+    type IntoIter = ::std::vec::IntoIter<ConfigError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            error.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigErrors {}
@@ -0,0 +1,205 @@
+//! Finds the line of a location like `groups[0].disallow[1]` with the parser of the format.
+//!
+//! The document is walked along the location and the walk fails on purpose at the value, the
+//! parser then adds the position of the value to the error.
+
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+use super::Format;
+
+/// The message of the error failing the walk at the value.
+const FOUND: &str = "location found";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Segment<'l> {
+    Key(&'l str),
+    Index(usize),
+}
+
+/// The line of the value at the location, or else of the closest value around it.
+#[cfg_attr(
+    not(any(feature = "toml", feature = "yaml", feature = "json")),
+    allow(unused_variables)
+)]
+pub(crate) fn locate(input: &str, format: Format, location: &str) -> Option<usize> {
+    let segments = segments(location)?;
+    (1..=segments.len()).rev().find_map(|len| {
+        let seek = Seek(&segments[..len]);
+        match format {
+            #[cfg(feature = "toml")]
+            Format::Toml => match seek.deserialize(toml::Deserializer::new(input)) {
+                Err(e) if e.message() == FOUND => e.span().map(|span| line_of(input, span.start)),
+                _ => None,
+            },
+            #[cfg(feature = "yaml")]
+            Format::Yaml => match seek.deserialize(serde_yaml::Deserializer::from_str(input)) {
+                Err(e) if e.to_string().contains(FOUND) => e.location().map(|l| l.line()),
+                _ => None,
+            },
+            #[cfg(feature = "json")]
+            Format::Json => {
+                match seek.deserialize(&mut serde_json::Deserializer::from_str(input)) {
+                    Err(e) if e.to_string().contains(FOUND) => Some(e.line()),
+                    _ => None,
+                }
+            }
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    })
+}
+
+#[cfg(feature = "toml")]
+pub(crate) fn line_of(input: &str, offset: usize) -> usize {
+    input[..offset.min(input.len())].matches('\n').count() + 1
+}
+
+/// The keys and indexes of a location, `None` if it isn't one.
+fn segments(location: &str) -> Option<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    for part in location.split('.').filter(|part| !part.is_empty()) {
+        let (key, indexes) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        }
+        for index in indexes.split('[').skip(1) {
+            let index = index.strip_suffix(']')?.parse().ok()?;
+            segments.push(Segment::Index(index));
+        }
+    }
+    Some(segments)
+}
+
+/// Walks to the value at the segments and fails there, succeeds if there is none.
+struct Seek<'s, 'l>(&'s [Segment<'l>]);
+
+impl Seek<'_, '_> {
+    fn scalar<E>(self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        match self.0.is_empty() {
+            true => Err(E::custom(FOUND)),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Seek<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Seek<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let (key, rest) = match self.0.split_first() {
+            None => return Err(de::Error::custom(FOUND)),
+            Some((Segment::Key(key), rest)) => (*key, rest),
+            Some((Segment::Index(_), _)) => return Ok(()),
+        };
+        while let Some(other) = map.next_key::<String>()? {
+            match other == key {
+                true => return map.next_value_seed(Seek(rest)),
+                false => map.next_value::<IgnoredAny>().map(drop)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let (index, rest) = match self.0.split_first() {
+            None => return Err(de::Error::custom(FOUND)),
+            Some((Segment::Index(index), rest)) => (*index, rest),
+            Some((Segment::Key(_), _)) => return Ok(()),
+        };
+        for _ in 0..index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+        }
+        seq.next_element_seed(Seek(rest)).map(drop)
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.scalar()
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.scalar()
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.scalar()
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.scalar()
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.scalar()
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.scalar()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments() {
+        assert_eq!(
+            super::segments("environments.staging.groups[1].disallow[0]"),
+            Some(vec![
+                Segment::Key("environments"),
+                Segment::Key("staging"),
+                Segment::Key("groups"),
+                Segment::Index(1),
+                Segment::Key("disallow"),
+                Segment::Index(0),
+            ])
+        );
+        assert_eq!(super::segments("groups[x]"), None);
+    }
+}
@@ -0,0 +1,516 @@
+//! Declaring a robots.txt in TOML, YAML or JSON instead of code.
+//!
+//! ```toml
+//! host = "example.com"
+//! sitemaps = ["https://example.com/sitemap.xml"]
+//!
+//! [[groups]]
+//! agents = ["*"]
+//! allow = ["/private/press"]
+//! disallow = ["/private"]
+//! crawl_delay = 5
+//!
+//! [environments.staging]
+//! disallow_all = true
+//! ```
+//!
+//! Each format needs its feature: `toml`, `yaml` or `json`.
+
+pub mod error;
+mod locate;
+
+pub use self::error::*;
+
+use std::{borrow::Cow, collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+use unicase::UniCase;
+use url::Url;
+
+use self::locate::*;
+use crate::{builder::*, parse::split_rr, parts::*};
+
+/// A JSON Schema of the configuration, for editors to validate and complete it.
+pub const JSON_SCHEMA: &str = include_str!("schema.json");
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub sitemaps: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<GroupConfig>,
+    /// Overrides by the name of the environment.
+    #[serde(default)]
+    pub environments: BTreeMap<String, EnvironmentConfig>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GroupConfig {
+    pub agents: Vec<String>,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub disallow: Vec<String>,
+    #[serde(default)]
+    pub crawl_delay: Option<f64>,
+    /// `requests/seconds`, e.g. `1/10`.
+    #[serde(default)]
+    pub request_rate: Option<String>,
+    /// `hhmm-hhmm` in UTC.
+    #[serde(default)]
+    pub visit_time: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentConfig {
+    /// Replaces all the groups with one disallowing everything.
+    #[serde(default)]
+    pub disallow_all: bool,
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Replaces the sitemaps.
+    #[serde(default)]
+    pub sitemaps: Option<Vec<String>>,
+    /// Replace the groups with the same agents, the others are added.
+    #[serde(default)]
+    pub groups: Vec<GroupConfig>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Format {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl Format {
+    /// The format by the file extension.
+    pub fn from_path<P>(path: P) -> Option<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match UniCase::new(extension) {
+            e if e == UniCase::new("toml") => Some(Format::Toml),
+            e if e == UniCase::new("yaml") || e == UniCase::new("yml") => Some(Format::Yaml),
+            e if e == UniCase::new("json") => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads a configuration, syntax errors and unknown keys come with their line.
+    #[cfg_attr(
+        not(any(feature = "toml", feature = "yaml", feature = "json")),
+        allow(unused_variables)
+    )]
+    pub fn parse(input: &str, format: Format) -> Result<Self, ConfigErrors> {
+        let result: Result<Self, (Option<usize>, String)> = match format {
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::from_str(input).map_err(|e| {
+                let line = e.span().map(|span| line_of(input, span.start));
+                (line, e.message().to_string())
+            }),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_str(input).map_err(|e| {
+                let line = e.location().map(|location| location.line());
+                (line, strip_position(&e.to_string()))
+            }),
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::from_str(input)
+                .map_err(|e| (Some(e.line()), strip_position(&e.to_string()))),
+            #[allow(unreachable_patterns)]
+            format => Err((None, format!("{:?} support is not enabled", format))),
+        };
+        result.map_err(|(line, message)| {
+            ConfigErrors::from(ConfigError {
+                line,
+                location: String::new(),
+                message,
+            })
+        })
+    }
+
+    /// Reads a configuration and builds the robots.txt for the environment, or the base one.
+    ///
+    /// The lines of errors are those of the values at their locations.
+    pub fn render(
+        input: &str,
+        format: Format,
+        environment: Option<&str>,
+    ) -> Result<Robots<'static>, ConfigErrors> {
+        let config = Config::parse(input, format)?;
+        config.build_for(environment).map_err(|errors| {
+            ConfigErrors(
+                errors
+                    .into_iter()
+                    .map(|mut error| {
+                        error.line = error
+                            .line
+                            .or_else(|| locate(input, format, &error.location));
+                        error
+                    })
+                    .collect(),
+            )
+        })
+    }
+
+    /// The robots.txt without any environment overrides.
+    pub fn build(&self) -> Result<Robots<'static>, ConfigErrors> {
+        self.build_for(None)
+    }
+
+    pub fn build_for(&self, environment: Option<&str>) -> Result<Robots<'static>, ConfigErrors> {
+        let mut errors = Vec::new();
+        let mut host = self.host.as_ref();
+        let mut sitemaps: Vec<(String, &String)> = self
+            .sitemaps
+            .iter()
+            .enumerate()
+            .map(|(i, url)| (format!("sitemaps[{}]", i), url))
+            .collect();
+        let mut groups: Vec<(String, &GroupConfig)> = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(i, group)| (format!("groups[{}]", i), group))
+            .collect();
+
+        let disallow_all = GroupConfig {
+            agents: vec!["*".to_string()],
+            disallow: vec!["/".to_string()],
+            ..GroupConfig::default()
+        };
+        if let Some(name) = environment {
+            let overrides = match self.environments.get(name) {
+                Some(overrides) => overrides,
+                None => {
+                    let error = ConfigError::new("environments", format!("unknown `{}`", name));
+                    return Err(error.into());
+                }
+            };
+            let prefix = format!("environments.{}", name);
+            host = overrides.host.as_ref().or(host);
+            if let Some(ref urls) = overrides.sitemaps {
+                sitemaps = urls
+                    .iter()
+                    .enumerate()
+                    .map(|(i, url)| (format!("{}.sitemaps[{}]", prefix, i), url))
+                    .collect();
+            }
+            for (i, group) in overrides.groups.iter().enumerate() {
+                let location = format!("{}.groups[{}]", prefix, i);
+                match groups
+                    .iter_mut()
+                    .find(|(_, other)| same_agents(&other.agents, &group.agents))
+                {
+                    Some(other) => *other = (location, group),
+                    None => groups.push((location, group)),
+                }
+            }
+            if overrides.disallow_all {
+                groups = vec![(format!("{}.disallow_all", prefix), &disallow_all)];
+            }
+        }
+
+        let mut builder = RobotsBuilder::new();
+        if let Some(host) = host {
+            builder = builder.host(Cow::Owned(host.clone()));
+        }
+        let mut urls = Vec::new();
+        for (location, url) in sitemaps {
+            match Url::parse(url) {
+                Ok(url) => urls.push(url),
+                Err(e) => errors.push(ConfigError::new(location, format!("`{}`: {}", url, e))),
+            }
+        }
+
+        // The groups behind `Robots::sections`, the ones for `*` are merged.
+        let mut sections: Vec<(&str, &GroupConfig)> = Vec::new();
+        let mut defaults: Vec<(&str, &GroupConfig)> = Vec::new();
+        for (location, group) in &groups {
+            match group.agents.iter().any(|agent| agent == "*") {
+                true => defaults.push((location, group)),
+                false => sections.push((location, group)),
+            }
+            let mut section = SectionBuilder::new(builder);
+            for agent in &group.agents {
+                section = section.useragent(agent.clone());
+            }
+            for path in &group.allow {
+                section = section.allow(PathPattern::new(path.clone()));
+            }
+            for path in &group.disallow {
+                section = section.disallow(PathPattern::new(path.clone()));
+            }
+            if let Some(delay) = group.crawl_delay {
+                section = section.crawl_delay(delay);
+            }
+            if let Some(ref rate) = group.request_rate {
+                let parsed = split_rr(rate).and_then(|(requests, seconds)| {
+                    Some((requests.parse().ok()?, seconds.parse().ok()?))
+                });
+                match parsed {
+                    Some((requests, seconds)) => section = section.request_rate(requests, seconds),
+                    None => errors.push(ConfigError::new(
+                        format!("{}.request_rate", location),
+                        format!("invalid request rate `{}`", rate),
+                    )),
+                }
+            }
+            if let Some(ref time) = group.visit_time {
+                match VisitTime::parse(time) {
+                    Some(time) => section = section.visit_time(time),
+                    None => errors.push(ConfigError::new(
+                        format!("{}.visit_time", location),
+                        format!("invalid visit time `{}`", time),
+                    )),
+                }
+            }
+            builder = section.end_section();
+        }
+
+        match builder.try_build() {
+            Ok(mut robots) if errors.is_empty() => {
+                robots.default_section.sitemaps.extend(urls);
+                Ok(robots)
+            }
+            Ok(_) => Err(ConfigErrors(errors)),
+            Err(build_errors) => {
+                for error in build_errors {
+                    let groups = match error.section {
+                        Some(i) => &sections[i..=i],
+                        None => &defaults[..],
+                    };
+                    errors.push(build_error(groups, error.kind));
+                }
+                Err(ConfigErrors(errors))
+            }
+        }
+    }
+}
+
+/// Maps a problem in a built group back to the configuration.
+fn build_error(groups: &[(&str, &GroupConfig)], kind: BuildErrorKind) -> ConfigError {
+    let find = |key: &str, values: fn(&GroupConfig) -> &Vec<String>, value: &str| {
+        groups.iter().find_map(|(location, group)| {
+            values(group)
+                .iter()
+                .position(|other| other == value)
+                .map(|i| format!("{}.{}[{}]", location, key, i))
+        })
+    };
+    let first = groups
+        .first()
+        .map(|(location, _)| location.to_string())
+        .unwrap_or_default();
+    let location = match kind {
+        BuildErrorKind::InvalidPath(ref path, _) => {
+            find("allow", |g| &g.allow, path).or_else(|| find("disallow", |g| &g.disallow, path))
+        }
+        BuildErrorKind::InvalidUserAgent(ref agent) => find("agents", |g| &g.agents, agent),
+        BuildErrorKind::NoUserAgents => Some(format!("{}.agents", first)),
+        BuildErrorKind::InvalidCrawlDelay(_) => Some(format!("{}.crawl_delay", first)),
+        BuildErrorKind::InvalidRequestRate(_) => Some(format!("{}.request_rate", first)),
+        BuildErrorKind::DanglingRuleComment(_) | BuildErrorKind::DanglingTrailingComment(_) => None,
+    };
+    ConfigError::new(location.unwrap_or(first), kind)
+}
+
+fn same_agents(a: &[String], b: &[String]) -> bool {
+    let key = |agents: &[String]| {
+        let mut agents: Vec<_> = agents.iter().map(|agent| agent.to_lowercase()).collect();
+        agents.sort();
+        agents
+    };
+    key(a) == key(b)
+}
+
+/// Leaves out the ` at line 3 column 5` the parsers add to their messages.
+#[cfg(any(feature = "json", feature = "yaml"))]
+fn strip_position(message: &str) -> String {
+    match message.find(" at line ") {
+        Some(pos) => message[..pos].to_string(),
+        None => message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXPECTED: &str = "\
+User-agent: badbot
+Disallow: /
+
+User-agent: *
+Allow: /private/press
+Disallow: /private
+Crawl-delay: 5
+Request-rate: 1/10
+Sitemap: https://example.com/sitemap.xml
+
+Host: example.com
+";
+
+    static STAGING: &str = "\
+User-agent: *
+Disallow: /
+Sitemap: https://staging.example.com/sitemap.xml
+
+Host: example.com
+";
+
+    #[cfg(feature = "toml")]
+    static TOML: &str = r#"
+host = "example.com"
+sitemaps = ["https://example.com/sitemap.xml"]
+
+[[groups]]
+agents = ["*"]
+allow = ["/private/press"]
+disallow = ["/private"]
+crawl_delay = 5
+request_rate = "1/10"
+
+[[groups]]
+agents = ["badbot"]
+disallow = ["/"]
+
+[environments.staging]
+disallow_all = true
+sitemaps = ["https://staging.example.com/sitemap.xml"]
+"#;
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml() {
+        let robots = Config::render(TOML, Format::Toml, None).unwrap();
+        assert_eq!(EXPECTED, robots.to_string());
+        let robots = Config::render(TOML, Format::Toml, Some("staging")).unwrap();
+        assert_eq!(STAGING, robots.to_string());
+
+        let errors = Config::render(TOML, Format::Toml, Some("production")).unwrap_err();
+        assert_eq!(
+            "line 16: environments: unknown `production`",
+            errors.to_string()
+        );
+
+        let input = TOML.replace("\"/private\"]", "\"private\"]\nvisit_time = \"noon\"");
+        let errors: Vec<_> = Config::render(&input, Format::Toml, None)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "line 9: groups[0].visit_time: invalid visit time `noon`",
+                "line 8: groups[0].disallow[0]: invalid path `private`: must start with `/` or `*`",
+            ],
+            errors
+        );
+
+        let errors =
+            Config::render("[[groups]]\nagent = [\"*\"]\n", Format::Toml, None).unwrap_err();
+        assert_eq!(Some(2), errors.errors()[0].line);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml() {
+        let input = r#"
+host: example.com
+sitemaps:
+  - https://example.com/sitemap.xml
+groups:
+  - agents: ["*"]
+    allow: [/private/press]
+    disallow:
+      - /private
+    crawl_delay: 5
+    request_rate: 1/10
+  - agents: [badbot]
+    disallow: [/]
+environments:
+  staging:
+    groups:
+      - agents: [BadBot]
+        disallow: [/tmp]
+"#;
+        let robots = Config::render(input, Format::Yaml, None).unwrap();
+        assert_eq!(EXPECTED, robots.to_string());
+        let robots = Config::render(input, Format::Yaml, Some("staging")).unwrap();
+        assert!(robots
+            .to_string()
+            .starts_with("User-agent: BadBot\nDisallow: /tmp\n"));
+
+        let input = input.replace("      - /private", "      - private");
+        let errors = Config::render(&input, Format::Yaml, None).unwrap_err();
+        assert_eq!(Some(9), errors.errors()[0].line);
+
+        // The same value earlier in the file is another one.
+        let input = r#"
+groups:
+  - agents:
+      - tmp
+    disallow: [/]
+environments:
+  staging:
+    groups:
+      - agents: [other]
+        disallow:
+          - tmp
+"#;
+        let errors = Config::render(input, Format::Yaml, Some("staging")).unwrap_err();
+        assert_eq!(
+            "line 11: environments.staging.groups[0].disallow[0]: \
+             invalid path `tmp`: must start with `/` or `*`",
+            errors.errors()[0].to_string()
+        );
+
+        let errors = Config::render("groups: 5\n", Format::Yaml, None).unwrap_err();
+        assert_eq!(Some(1), errors.errors()[0].line);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        let input = r#"{
+  "groups": [
+    { "agents": ["*"], "disallow": ["/private"] },
+    { "agents": ["bad bot"], "crawl_delay": -1 }
+  ]
+}"#;
+        let errors: Vec<_> = Config::render(input, Format::Json, None)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "line 4: groups[1].agents[0]: invalid user agent `bad bot`",
+                "line 4: groups[1].crawl_delay: invalid crawl delay `-1`",
+            ],
+            errors
+        );
+
+        let errors = Config::render("{\n  \"hosts\": 1\n}", Format::Json, None).unwrap_err();
+        assert_eq!(Some(2), errors.errors()[0].line);
+
+        let schema: serde_json::Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+        assert_eq!("object", schema["type"]);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(Some(Format::Yaml), Format::from_path("robots.YML"));
+        assert_eq!(Some(Format::Toml), Format::from_path("conf/robots.toml"));
+        assert_eq!(None, Format::from_path("robots.txt"));
+    }
+}
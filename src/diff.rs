@@ -0,0 +1,681 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use unicase::UniCase;
+use url::Url;
+
+use crate::{
+    analysis::compare_rules,
+    matcher::RuleMatching,
+    parts::*,
+    policy::{AgentPolicy, Semantics},
+};
+
+/// A change in what one agent obeys.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AgentChange {
+    RuleAdded {
+        allow: bool,
+        path: String,
+    },
+    RuleRemoved {
+        allow: bool,
+        path: String,
+    },
+    CrawlDelay {
+        old: Option<f64>,
+        new: Option<f64>,
+    },
+    RequestRate {
+        old: Option<RequestRate>,
+        new: Option<RequestRate>,
+    },
+    VisitTime {
+        old: Option<VisitTime>,
+        new: Option<VisitTime>,
+    },
+    /// A `Content-Signal` or `Content-Usage` line, with its value as written.
+    UsageAdded {
+        directive: UsageDirective,
+        value: String,
+    },
+    UsageRemoved {
+        directive: UsageDirective,
+        value: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentDiff {
+    /// A user agent named in either file, `*` for all the others.
+    pub agent: String,
+    pub changes: Vec<AgentChange>,
+}
+
+/// The changes in meaning between two robots.txt files.
+///
+/// Grouping, comments and formatting are not compared, nor is rule order
+/// unless the first match decides.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RobotsDiff {
+    pub agents: Vec<AgentDiff>,
+    pub sitemaps_added: Vec<Url>,
+    pub sitemaps_removed: Vec<Url>,
+    /// The old and the new `Host`, if it changed.
+    pub host: Option<(Option<String>, Option<String>)>,
+}
+
+impl RobotsDiff {
+    pub fn new(old: &Robots<'_>, new: &Robots<'_>, semantics: Semantics) -> Self {
+        let mut agents: BTreeMap<UniCase<String>, String> = BTreeMap::new();
+        for section in new.sections.iter().chain(&old.sections) {
+            for ua in &section.useragents {
+                agents
+                    .entry(UniCase::new(ua.to_string()))
+                    .or_insert_with(|| ua.to_string());
+            }
+        }
+
+        let mut diff = RobotsDiff::default();
+        for agent in Some("*")
+            .into_iter()
+            .chain(agents.values().map(String::as_str))
+        {
+            let changes = agent_changes(
+                &AgentPolicy::new(old, agent, semantics.agents),
+                &AgentPolicy::new(new, agent, semantics.agents),
+                semantics.rules,
+            );
+            if !changes.is_empty() {
+                diff.agents.push(AgentDiff {
+                    agent: agent.to_string(),
+                    changes,
+                });
+            }
+        }
+
+        let (old_sitemaps, new_sitemaps) = (sitemaps(old), sitemaps(new));
+        diff.sitemaps_added = new_sitemaps.difference(&old_sitemaps).cloned().collect();
+        diff.sitemaps_removed = old_sitemaps.difference(&new_sitemaps).cloned().collect();
+        if old.host != new.host {
+            diff.host = Some((
+                old.host.as_ref().map(|host| host.to_string()),
+                new.host.as_ref().map(|host| host.to_string()),
+            ));
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+            && self.sitemaps_added.is_empty()
+            && self.sitemaps_removed.is_empty()
+            && self.host.is_none()
+    }
+
+    /// The diff as a JSON object.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"agents\":[");
+        for (i, agent) in self.agents.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"agent\":");
+            json_string(&mut out, &agent.agent);
+            out.push_str(",\"changes\":[");
+            for (j, change) in agent.changes.iter().enumerate() {
+                if j > 0 {
+                    out.push(',');
+                }
+                json_change(&mut out, change);
+            }
+            out.push_str("]}");
+        }
+        out.push_str("],\"sitemaps_added\":");
+        json_urls(&mut out, &self.sitemaps_added);
+        out.push_str(",\"sitemaps_removed\":");
+        json_urls(&mut out, &self.sitemaps_removed);
+        out.push_str(",\"host\":");
+        match self.host.as_ref() {
+            Some((old, new)) => {
+                out.push_str("{\"old\":");
+                json_option(&mut out, old.as_ref());
+                out.push_str(",\"new\":");
+                json_option(&mut out, new.as_ref());
+                out.push('}');
+            }
+            None => out.push_str("null"),
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// A changelog, one line per change.
+impl fmt::Display for RobotsDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for agent in &self.agents {
+            writeln!(f, "[{}]", agent.agent)?;
+            for change in &agent.changes {
+                match change {
+                    AgentChange::RuleAdded { allow, path } => {
+                        writeln!(f, "+ {}: {}", rule_name(*allow), path)?
+                    }
+                    AgentChange::RuleRemoved { allow, path } => {
+                        writeln!(f, "- {}: {}", rule_name(*allow), path)?
+                    }
+                    AgentChange::CrawlDelay { old, new } => {
+                        writeln!(f, "~ Crawl-delay: {} -> {}", Shown(old), Shown(new))?
+                    }
+                    AgentChange::RequestRate { old, new } => writeln!(
+                        f,
+                        "~ Request-rate: {} -> {}",
+                        Shown(&old.map(|rate| format!("{}/{}", rate.requests, rate.seconds))),
+                        Shown(&new.map(|rate| format!("{}/{}", rate.requests, rate.seconds)))
+                    )?,
+                    AgentChange::VisitTime { old, new } => {
+                        writeln!(f, "~ Visit-time: {} -> {}", Shown(old), Shown(new))?
+                    }
+                    AgentChange::UsageAdded { directive, value } => {
+                        writeln!(f, "+ {}: {}", usage_name(*directive), value)?
+                    }
+                    AgentChange::UsageRemoved { directive, value } => {
+                        writeln!(f, "- {}: {}", usage_name(*directive), value)?
+                    }
+                }
+            }
+        }
+        for url in &self.sitemaps_added {
+            writeln!(f, "+ Sitemap: {}", url)?;
+        }
+        for url in &self.sitemaps_removed {
+            writeln!(f, "- Sitemap: {}", url)?;
+        }
+        if let Some((old, new)) = self.host.as_ref() {
+            writeln!(f, "~ Host: {} -> {}", Shown(old), Shown(new))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Robots<'a> {
+    /// What changed in meaning from this file to the other one.
+    pub fn diff(&self, other: &Robots<'_>, semantics: Semantics) -> RobotsDiff {
+        RobotsDiff::new(self, other, semantics)
+    }
+}
+
+fn agent_changes(
+    old: &AgentPolicy<'_>,
+    new: &AgentPolicy<'_>,
+    matching: RuleMatching,
+) -> Vec<AgentChange> {
+    let mut changes = match compare_rules(&old.rules, &new.rules, matching).is_equivalent() {
+        true => Vec::new(),
+        false => rule_changes(&old.rules, &new.rules, matching),
+    };
+    if old.crawl_delay != new.crawl_delay {
+        changes.push(AgentChange::CrawlDelay {
+            old: old.crawl_delay,
+            new: new.crawl_delay,
+        });
+    }
+    if old.req_rate != new.req_rate {
+        changes.push(AgentChange::RequestRate {
+            old: old.req_rate,
+            new: new.req_rate,
+        });
+    }
+    if old.visit_time != new.visit_time {
+        changes.push(AgentChange::VisitTime {
+            old: old.visit_time,
+            new: new.visit_time,
+        });
+    }
+    let (old_usage, new_usage) = (usage_lines(&old.usage), usage_lines(&new.usage));
+    changes.extend(
+        new_usage
+            .iter()
+            .filter(|(key, _)| !old_usage.contains_key(key))
+            .map(|((directive, _, _), value)| AgentChange::UsageAdded {
+                directive: *directive,
+                value: value.clone(),
+            }),
+    );
+    changes.extend(
+        old_usage
+            .iter()
+            .filter(|(key, _)| !new_usage.contains_key(key))
+            .map(|((directive, _, _), value)| AgentChange::UsageRemoved {
+                directive: *directive,
+                value: value.clone(),
+            }),
+    );
+    changes
+}
+
+/// A preference by its directive, path and terms.
+type UsageKey = (UsageDirective, Option<String>, Vec<(String, bool)>);
+
+/// The preferences regardless of their order, the order of their terms and the case of terms.
+fn usage_lines(usage: &[UsagePreference<'_>]) -> BTreeMap<UsageKey, String> {
+    usage
+        .iter()
+        .map(|pref| {
+            let mut signals: Vec<_> = pref
+                .signals
+                .iter()
+                .map(|(term, allowed)| (term.to_lowercase(), *allowed))
+                .collect();
+            signals.sort();
+            let path = pref.path.as_ref().map(|path| path.to_string());
+            ((pref.directive, path, signals), pref.to_string())
+        })
+        .collect()
+}
+
+/// The rules removed and added, where the lists decide some path differently.
+fn rule_changes(old: &[Rule<'_>], new: &[Rule<'_>], matching: RuleMatching) -> Vec<AgentChange> {
+    let rules = |rules: &[Rule<'_>]| -> Vec<(bool, String)> {
+        rules
+            .iter()
+            .map(|rule| (rule.allow, rule.path.to_string()))
+            .collect()
+    };
+    let (old, new) = (rules(old), rules(new));
+    let added = |(allow, path): &(bool, String)| AgentChange::RuleAdded {
+        allow: *allow,
+        path: path.clone(),
+    };
+    let removed = |(allow, path): &(bool, String)| AgentChange::RuleRemoved {
+        allow: *allow,
+        path: path.clone(),
+    };
+    match matching {
+        // The lists are compared in order, as an empty rule or a moved one changes decisions.
+        RuleMatching::FirstMatch => {
+            // The length of the longest common subsequence of the lists from each position on.
+            let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+            for i in (0..old.len()).rev() {
+                for j in (0..new.len()).rev() {
+                    common[i][j] = match old[i] == new[j] {
+                        true => common[i + 1][j + 1] + 1,
+                        false => common[i + 1][j].max(common[i][j + 1]),
+                    };
+                }
+            }
+            let (mut i, mut j) = (0, 0);
+            let mut changes = Vec::new();
+            while i < old.len() || j < new.len() {
+                if i < old.len() && j < new.len() && old[i] == new[j] {
+                    i += 1;
+                    j += 1;
+                } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1])
+                {
+                    changes.push(removed(&old[i]));
+                    i += 1;
+                } else {
+                    changes.push(added(&new[j]));
+                    j += 1;
+                }
+            }
+            changes
+        }
+        // Empty rules match nothing, so they make no difference.
+        RuleMatching::LongestMatch => {
+            let set = |rules: Vec<(bool, String)>| -> BTreeSet<(bool, String)> {
+                rules
+                    .into_iter()
+                    .filter(|(_, path)| !path.is_empty())
+                    .collect()
+            };
+            let (old, new) = (set(old), set(new));
+            new.difference(&old)
+                .map(added)
+                .chain(old.difference(&new).map(removed))
+                .collect()
+        }
+    }
+}
+
+fn sitemaps(robots: &Robots<'_>) -> BTreeSet<Url> {
+    robots
+        .sections
+        .iter()
+        .chain(Some(&robots.default_section))
+        .flat_map(|section| section.sitemaps.iter().cloned())
+        .collect()
+}
+
+fn rule_name(allow: bool) -> &'static str {
+    match allow {
+        true => "Allow",
+        false => "Disallow",
+    }
+}
+
+fn usage_name(directive: UsageDirective) -> &'static str {
+    match directive {
+        UsageDirective::ContentSignal => "Content-Signal",
+        UsageDirective::ContentUsage => "Content-Usage",
+    }
+}
+
+/// Shows a missing value as `none`.
+struct Shown<'v, T>(&'v Option<T>);
+
+impl<'v, T> fmt::Display for Shown<'v, T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(value) => value.fmt(f),
+            None => f.write_str("none"),
+        }
+    }
+}
+
+fn json_change(out: &mut String, change: &AgentChange) {
+    match change {
+        AgentChange::RuleAdded { allow, path } | AgentChange::RuleRemoved { allow, path } => {
+            out.push_str(match change {
+                AgentChange::RuleAdded { .. } => "{\"type\":\"rule_added\"",
+                _ => "{\"type\":\"rule_removed\"",
+            });
+            out.push_str(",\"allow\":");
+            out.push_str(if *allow { "true" } else { "false" });
+            out.push_str(",\"path\":");
+            json_string(out, path);
+        }
+        AgentChange::CrawlDelay { old, new } => {
+            out.push_str("{\"type\":\"crawl_delay\",\"old\":");
+            json_number(out, *old);
+            out.push_str(",\"new\":");
+            json_number(out, *new);
+        }
+        AgentChange::RequestRate { old, new } => {
+            out.push_str("{\"type\":\"request_rate\",\"old\":");
+            json_rate(out, *old);
+            out.push_str(",\"new\":");
+            json_rate(out, *new);
+        }
+        AgentChange::VisitTime { old, new } => {
+            out.push_str("{\"type\":\"visit_time\",\"old\":");
+            json_option(out, old.map(|time| time.to_string()).as_ref());
+            out.push_str(",\"new\":");
+            json_option(out, new.map(|time| time.to_string()).as_ref());
+        }
+        AgentChange::UsageAdded { directive, value }
+        | AgentChange::UsageRemoved { directive, value } => {
+            out.push_str(match change {
+                AgentChange::UsageAdded { .. } => "{\"type\":\"usage_added\"",
+                _ => "{\"type\":\"usage_removed\"",
+            });
+            out.push_str(",\"directive\":");
+            json_string(out, usage_name(*directive));
+            out.push_str(",\"value\":");
+            json_string(out, value);
+        }
+    }
+    out.push('}');
+}
+
+fn json_rate(out: &mut String, rate: Option<RequestRate>) {
+    match rate {
+        Some(rate) => out.push_str(&format!(
+            "{{\"requests\":{},\"seconds\":{}}}",
+            rate.requests, rate.seconds
+        )),
+        None => out.push_str("null"),
+    }
+}
+
+fn json_number(out: &mut String, number: Option<f64>) {
+    match number {
+        Some(number) if number.is_finite() => out.push_str(&number.to_string()),
+        _ => out.push_str("null"),
+    }
+}
+
+fn json_urls(out: &mut String, urls: &[Url]) {
+    out.push('[');
+    for (i, url) in urls.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json_string(out, url.as_str());
+    }
+    out.push(']');
+}
+
+fn json_option(out: &mut String, value: Option<&String>) {
+    match value {
+        Some(value) => json_string(out, value),
+        None => out.push_str("null"),
+    }
+}
+
+pub(crate) fn json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static OLD: &str = r#"
+# Old version
+User-agent: *
+Disallow: /tmp/
+Disallow: /private
+Sitemap: http://example.com/sitemap.xml
+
+User-agent: bingbot
+User-agent: msnbot
+Disallow: /search
+Crawl-delay: 5
+"#;
+
+    #[test]
+    fn formatting_and_order() {
+        let new = r#"
+user-agent: msnbot
+disallow: /search
+crawl-delay: 5.0
+
+USER-AGENT: *
+DISALLOW: /private
+DISALLOW: /tmp/
+
+user-agent: bingbot
+disallow: /search
+crawl-delay: 5
+sitemap: http://example.com/sitemap.xml
+"#;
+        for &semantics in &[Semantics::legacy(), Semantics::rfc9309()] {
+            let diff = Robots::from_str_lossy(OLD).diff(&Robots::from_str_lossy(new), semantics);
+            assert!(diff.is_empty(), "{}", diff);
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let new = r#"
+User-agent: *
+Disallow: /private
+Disallow: /drafts
+Sitemap: http://example.com/sitemap-2.xml
+
+User-agent: bingbot
+Disallow: /search
+Crawl-delay: 10
+
+User-agent: msnbot
+Disallow: /search
+Crawl-delay: 5
+
+Host: example.com
+"#;
+        let diff =
+            Robots::from_str_lossy(OLD).diff(&Robots::from_str_lossy(new), Semantics::rfc9309());
+        assert_eq!(
+            diff.to_string(),
+            "\
+[*]
++ Disallow: /drafts
+- Disallow: /tmp/
+[bingbot]
+~ Crawl-delay: 5 -> 10
++ Sitemap: http://example.com/sitemap-2.xml
+- Sitemap: http://example.com/sitemap.xml
+~ Host: none -> example.com
+"
+        );
+        assert_eq!(
+            diff.to_json(),
+            concat!(
+                r#"{"agents":[{"agent":"*","changes":["#,
+                r#"{"type":"rule_added","allow":false,"path":"/drafts"},"#,
+                r#"{"type":"rule_removed","allow":false,"path":"/tmp/"}]},"#,
+                r#"{"agent":"bingbot","changes":[{"type":"crawl_delay","old":5,"new":10}]}],"#,
+                r#""sitemaps_added":["http://example.com/sitemap-2.xml"],"#,
+                r#""sitemaps_removed":["http://example.com/sitemap.xml"],"#,
+                r#""host":{"old":null,"new":"example.com"}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn new_group() {
+        let new = format!("{}\nUser-agent: badbot\nDisallow: /\n", OLD);
+        let diff =
+            Robots::from_str_lossy(OLD).diff(&Robots::from_str_lossy(&new), Semantics::rfc9309());
+        assert_eq!(1, diff.agents.len());
+        assert_eq!("badbot", diff.agents[0].agent);
+        assert_eq!(
+            vec![
+                AgentChange::RuleAdded {
+                    allow: false,
+                    path: "/".into()
+                },
+                AgentChange::RuleRemoved {
+                    allow: false,
+                    path: "/private".into()
+                },
+                AgentChange::RuleRemoved {
+                    allow: false,
+                    path: "/tmp/".into()
+                },
+            ],
+            diff.agents[0].changes
+        );
+    }
+
+    #[test]
+    fn rule_order() {
+        let diff = |old: &str, new: &str, semantics| {
+            let (old, new) = (
+                format!("User-agent: *\n{}", old),
+                format!("User-agent: *\n{}", new),
+            );
+            Robots::from_str_lossy(&old)
+                .diff(&Robots::from_str_lossy(&new), semantics)
+                .to_string()
+        };
+        let (old, new) = ("Allow: /a/b\nDisallow: /a", "Disallow: /a\nAllow: /a/b");
+        assert_eq!(
+            "[*]\n- Allow: /a/b\n+ Allow: /a/b\n",
+            diff(old, new, Semantics::legacy())
+        );
+        assert_eq!("", diff(old, new, Semantics::rfc9309()));
+
+        // A leading empty rule allows everything under the first match only.
+        let (old, new) = ("Disallow:\nDisallow: /a", "Disallow: /a");
+        assert_eq!("[*]\n- Disallow: \n", diff(old, new, Semantics::legacy()));
+        assert_eq!("", diff(old, new, Semantics::rfc9309()));
+
+        assert_eq!(
+            "",
+            diff("Disallow: /a*", "Disallow: /a", Semantics::rfc9309())
+        );
+    }
+
+    #[test]
+    fn usage() {
+        let old = "\
+User-agent: *
+Content-Signal: search=yes, ai-train=no
+Content-Usage: /docs/ train-ai=n
+";
+        let new = "\
+User-agent: *
+Content-Usage: /docs/ train-ai=n
+Content-Signal: search=yes, ai-train=yes
+";
+        let diff =
+            Robots::from_str_lossy(old).diff(&Robots::from_str_lossy(new), Semantics::rfc9309());
+        assert_eq!(
+            diff.to_string(),
+            "\
+[*]
++ Content-Signal: search=yes, ai-train=yes
+- Content-Signal: search=yes, ai-train=no
+"
+        );
+        assert_eq!(
+            diff.to_json(),
+            concat!(
+                r#"{"agents":[{"agent":"*","changes":["#,
+                r#"{"type":"usage_added","directive":"Content-Signal","#,
+                r#""value":"search=yes, ai-train=yes"},"#,
+                r#"{"type":"usage_removed","directive":"Content-Signal","#,
+                r#""value":"search=yes, ai-train=no"}]}],"#,
+                r#""sitemaps_added":[],"sitemaps_removed":[],"host":null}"#
+            )
+        );
+
+        // Neither does the order of the terms nor their case.
+        let reordered = "\
+User-agent: *
+Content-Usage: /docs/ Train-AI=n
+Content-Signal: ai-train=no, SEARCH=yes
+";
+        assert!(Robots::from_str_lossy(old)
+            .diff(&Robots::from_str_lossy(reordered), Semantics::rfc9309())
+            .is_empty());
+
+        // The order of the lines doesn't matter, a borrowed preference is built as is.
+        let value = String::from("train-ai=n");
+        let preference = UsagePreference::parse(UsageDirective::ContentUsage, &value)
+            .unwrap()
+            .path("/docs/");
+        let built = crate::builder::RobotsBuilder::new()
+            .start_section("*")
+            .usage(preference)
+            .usage(
+                UsagePreference::new(UsageDirective::ContentSignal)
+                    .signal("search", true)
+                    .signal("ai-train", false),
+            )
+            .end_section()
+            .build();
+        assert!(Robots::from_str_lossy(old)
+            .diff(&built, Semantics::rfc9309())
+            .is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use url::Url;
+
+use crate::fetch::*;
+
+/// An async fetcher on top of [reqwest](https://docs.rs/reqwest).
+#[derive(Clone, Debug)]
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+    config: FetchConfig,
+}
+
+impl ReqwestFetcher {
+    pub fn new(config: FetchConfig) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(config.timeout)
+            .user_agent(config.user_agent.as_str())
+            .build()?;
+        Ok(ReqwestFetcher { client, config })
+    }
+
+    async fn request(
+        &self,
+        url: &Url,
+        validators: &Validators,
+    ) -> Result<Response, reqwest::Error> {
+        let mut request = self.client.get(url.clone());
+        for (name, value) in validators.headers() {
+            request = request.header(name, value);
+        }
+        let mut response = request.send().await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                Some((name.as_str().to_string(), value.to_string()))
+            })
+            .collect();
+        let limit = self.config.max_body_size;
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > limit {
+                break;
+            }
+        }
+        truncate_body(&mut body, limit);
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl AsyncRobotsFetcher for ReqwestFetcher {
+    fn config(&self) -> &FetchConfig {
+        &self.config
+    }
+
+    fn get<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, FetchOutcome> {
+        Box::pin(async move { self.get_conditional(url, &Validators::default()).await })
+    }
+
+    fn get_conditional<'a>(
+        &'a self,
+        url: &'a Url,
+        validators: &'a Validators,
+    ) -> BoxFuture<'a, FetchOutcome> {
+        Box::pin(async move {
+            match self.request(url, validators).await {
+                Ok(response) => FetchOutcome::Response(response),
+                Err(e) => FetchOutcome::NetworkError(e.to_string()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::fetch::test_server::*;
+
+    async fn fetch(fetcher: &ReqwestFetcher, addr: std::net::SocketAddr) -> FetchResult {
+        let url: Url = format!("http://{}/page", addr).parse().unwrap();
+        fetcher.fetch(&url).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn statuses() {
+        let fetcher = ReqwestFetcher::new(FetchConfig::default()).unwrap();
+
+        let addr = serve(|path| match path {
+            "/robots.txt" => Reply::new(302).header("Location", "/real-robots.txt"),
+            _ => Reply::new(200).body("User-agent: *\nDisallow: /\n"),
+        });
+        let result = fetch(&fetcher, addr).await;
+        assert_eq!(Availability::Available, result.availability);
+        assert_eq!("/real-robots.txt", result.url.path());
+        assert_eq!("/", result.robots.default_section.rules[0].path.as_str());
+
+        let addr = serve(|_| Reply::new(410));
+        assert_eq!(
+            Availability::Unavailable,
+            fetch(&fetcher, addr).await.availability
+        );
+
+        let addr = serve(|_| Reply::new(500));
+        assert_eq!(
+            Availability::Unreachable,
+            fetch(&fetcher, addr).await.availability
+        );
+    }
+
+    #[tokio::test]
+    async fn limits() {
+        let addr = serve(|_| Reply::new(200).body("User-agent: *\nDisallow: /a\nDisallow: /b\n"));
+        let fetcher = ReqwestFetcher::new(FetchConfig::new().max_body_size(30)).unwrap();
+        assert_eq!(
+            1,
+            fetch(&fetcher, addr)
+                .await
+                .robots
+                .default_section
+                .rules
+                .len()
+        );
+
+        let addr = serve(|_| Reply::new(200).delay(Duration::from_secs(2)));
+        let fetcher =
+            ReqwestFetcher::new(FetchConfig::new().timeout(Duration::from_millis(200))).unwrap();
+        let result = fetch(&fetcher, addr).await;
+        assert_eq!(Availability::Unreachable, result.availability);
+        assert_eq!(None, result.status);
+    }
+}
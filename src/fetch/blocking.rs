@@ -0,0 +1,120 @@
+use url::Url;
+
+use crate::fetch::*;
+
+/// A blocking fetcher on top of [ureq](https://docs.rs/ureq).
+#[derive(Debug)]
+pub struct UreqFetcher {
+    agent: ureq::Agent,
+    config: FetchConfig,
+}
+
+impl UreqFetcher {
+    pub fn new(config: FetchConfig) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .max_redirects(0)
+            .timeout_global(Some(config.timeout))
+            .user_agent(config.user_agent.as_str())
+            .build()
+            .into();
+        UreqFetcher { agent, config }
+    }
+}
+
+impl Default for UreqFetcher {
+    fn default() -> Self {
+        UreqFetcher::new(FetchConfig::default())
+    }
+}
+
+impl RobotsFetcher for UreqFetcher {
+    fn config(&self) -> &FetchConfig {
+        &self.config
+    }
+
+    fn get(&self, url: &Url) -> FetchOutcome {
+        self.get_conditional(url, &Validators::default())
+    }
+
+    fn get_conditional(&self, url: &Url, validators: &Validators) -> FetchOutcome {
+        let mut request = self.agent.get(url.as_str());
+        for (name, value) in validators.headers() {
+            request = request.header(name, value);
+        }
+        let mut response = match request.call() {
+            Ok(response) => response,
+            Err(e) => return FetchOutcome::NetworkError(e.to_string()),
+        };
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                Some((name.as_str().to_string(), value.to_string()))
+            })
+            .collect();
+        let body = match read_body(response.body_mut().as_reader(), self.config.max_body_size) {
+            Ok(body) => body,
+            Err(e) => return FetchOutcome::NetworkError(e.to_string()),
+        };
+        FetchOutcome::Response(Response {
+            status: response.status().as_u16(),
+            headers,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{fetch::test_server::*, matcher::SimpleMatcher};
+
+    fn fetch(fetcher: &UreqFetcher, addr: std::net::SocketAddr, path: &str) -> FetchResult {
+        let url: Url = format!("http://{}{}", addr, path).parse().unwrap();
+        fetcher.fetch(&url).unwrap()
+    }
+
+    #[test]
+    fn statuses() {
+        let addr = serve(|path| match path {
+            "/robots.txt" => Reply::new(301).header("Location", "/moved/robots.txt"),
+            "/moved/robots.txt" => Reply::new(200).body("User-agent: *\nDisallow: /private\n"),
+            _ => Reply::new(404),
+        });
+        let fetcher = UreqFetcher::default();
+        let result = fetch(&fetcher, addr, "/page");
+        assert_eq!(Availability::Available, result.availability);
+        assert_eq!("/moved/robots.txt", result.url.path());
+        let matcher = SimpleMatcher::new(&result.robots.default_section.rules);
+        assert!(!matcher.check_path("/private"));
+
+        let addr = serve(|_| Reply::new(404));
+        assert_eq!(
+            Availability::Unavailable,
+            fetch(&fetcher, addr, "/").availability
+        );
+
+        let addr = serve(|_| Reply::new(503));
+        let result = fetch(&fetcher, addr, "/");
+        assert_eq!(Availability::Unreachable, result.availability);
+        assert_eq!(Some(503), result.status);
+    }
+
+    #[test]
+    fn limits() {
+        let addr = serve(|_| Reply::new(200).body("User-agent: *\nDisallow: /a\nDisallow: /b\n"));
+        let fetcher = UreqFetcher::new(FetchConfig::new().max_body_size(30));
+        let result = fetch(&fetcher, addr, "/");
+        assert_eq!(1, result.robots.default_section.rules.len());
+
+        let addr = serve(|_| Reply::new(200).delay(Duration::from_secs(2)));
+        let fetcher = UreqFetcher::new(FetchConfig::new().timeout(Duration::from_millis(200)));
+        let result = fetch(&fetcher, addr, "/");
+        assert_eq!(Availability::Unreachable, result.availability);
+        assert_eq!(None, result.status);
+    }
+}
@@ -0,0 +1,14 @@
+#[cfg(feature = "reqwest")]
+pub mod asynchronous;
+#[cfg(feature = "ureq")]
+pub mod blocking;
+pub mod fetcher;
+pub mod outcome;
+#[cfg(all(test, any(feature = "ureq", feature = "reqwest")))]
+mod test_server;
+
+#[cfg(feature = "reqwest")]
+pub use self::asynchronous::*;
+#[cfg(feature = "ureq")]
+pub use self::blocking::*;
+pub use self::{fetcher::*, outcome::*};
@@ -0,0 +1,408 @@
+use std::time::Duration;
+
+use url::Url;
+
+use crate::{
+    fetch::body_hash,
+    parts::*,
+    payload::{PayloadDiagnostic, PayloadHandling},
+};
+
+/// How many redirects are followed before robots.txt is considered unavailable.
+pub const MAX_REDIRECTS: usize = 5;
+
+/// How long a host may be unreachable before its robots.txt is considered unavailable.
+pub const UNREACHABLE_LIMIT: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// An HTTP response to a robots.txt request.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Response {
+            status,
+            ..Response::default()
+        }
+    }
+
+    pub fn body<B>(mut self, body: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        self.body = body.into();
+        self
+    }
+
+    pub fn location<L>(self, location: L) -> Self
+    where
+        L: Into<String>,
+    {
+        self.with_header("Location", location)
+    }
+
+    pub fn with_header<N, V>(mut self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The value of the first header with this name, ignoring case.
+    pub fn find_header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+pub(crate) fn find_header<'h>(headers: &'h [(String, String)], name: &str) -> Option<&'h str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// The result of a single request, whatever transport made it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FetchOutcome {
+    Response(Response),
+    /// No response at all: DNS failure, refused connection, timeout and so on.
+    NetworkError(String),
+}
+
+/// [RFC 9309, section 2.3.1](https://www.rfc-editor.org/rfc/rfc9309.html#section-2.3.1)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Availability {
+    /// Fetched successfully, the policy is what the file says.
+    Available,
+    /// A client error or too many redirects, everything is allowed.
+    Unavailable,
+    /// A server or network error, everything is disallowed.
+    Unreachable,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FetchResult {
+    pub availability: Availability,
+    /// The status of the last response, `None` after a network error.
+    pub status: Option<u16>,
+    /// The URL of the last request.
+    pub url: Url,
+    /// The headers of the last response.
+    pub headers: Vec<(String, String)>,
+    pub robots: Robots<'static>,
+    /// Set if the body was not text, e.g. an HTML page served as robots.txt.
+    pub diagnostic: Option<PayloadDiagnostic>,
+    /// The `body_hash` of the robots.txt, if one was fetched.
+    pub source_hash: Option<u64>,
+}
+
+impl FetchResult {
+    pub fn find_header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Request this URL and pass the outcome to `FetchMachine::next`.
+    Fetch(Url),
+    Done(FetchResult),
+}
+
+impl Step {
+    fn with_body(mut self, diagnostic: Option<PayloadDiagnostic>, source_hash: u64) -> Self {
+        if let Step::Done(ref mut result) = self {
+            result.diagnostic = diagnostic;
+            result.source_hash = Some(source_hash);
+        }
+        self
+    }
+}
+
+/// Turns the outcomes of robots.txt requests into a policy.
+///
+/// The machine never touches the network, it only tells which URL to request next.
+#[derive(Clone, Debug)]
+pub struct FetchMachine {
+    url: Url,
+    redirects: usize,
+    max_redirects: usize,
+    unreachable_for: Duration,
+    unreachable_limit: Duration,
+    payload_handling: PayloadHandling,
+}
+
+impl FetchMachine {
+    /// Starts with the robots.txt URL itself.
+    pub fn new(url: Url) -> Self {
+        FetchMachine {
+            url,
+            redirects: 0,
+            max_redirects: MAX_REDIRECTS,
+            unreachable_for: Duration::from_secs(0),
+            unreachable_limit: UNREACHABLE_LIMIT,
+            payload_handling: PayloadHandling::default(),
+        }
+    }
+
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// How long the host has already been unreachable according to earlier attempts.
+    ///
+    /// Once this exceeds the limit, an unreachable host is treated as having no robots.txt.
+    pub fn unreachable_for(mut self, duration: Duration) -> Self {
+        self.unreachable_for = duration;
+        self
+    }
+
+    pub fn unreachable_limit(mut self, limit: Duration) -> Self {
+        self.unreachable_limit = limit;
+        self
+    }
+
+    /// What to do with a successful response whose body is not text.
+    pub fn payload_handling(mut self, handling: PayloadHandling) -> Self {
+        self.payload_handling = handling;
+        self
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn start(&self) -> Step {
+        Step::Fetch(self.url.clone())
+    }
+
+    pub fn next(&mut self, outcome: FetchOutcome) -> Step {
+        let response = match outcome {
+            FetchOutcome::Response(response) => response,
+            FetchOutcome::NetworkError(_) => return self.unreachable(None),
+        };
+        match response.status {
+            200..=299 => {
+                let (robots, diagnostic) = Robots::from_bytes(
+                    &response.body,
+                    response.find_header("content-type"),
+                    self.payload_handling,
+                );
+                let source_hash = body_hash(&response.body);
+                self.done(Availability::Available, Some(response), robots)
+                    .with_body(diagnostic, source_hash)
+            }
+            // Nothing here asks for one, so the server is confused and treated as failing.
+            304 => self.unreachable(Some(response)),
+            300..=399 => {
+                let location = response
+                    .find_header("location")
+                    .and_then(|location| self.url.join(location).ok());
+                match location {
+                    Some(location) if self.redirects < self.max_redirects => {
+                        self.redirects += 1;
+                        self.url = location;
+                        Step::Fetch(self.url.clone())
+                    }
+                    _ => self.done(
+                        Availability::Unavailable,
+                        Some(response),
+                        Robots::allow_all(),
+                    ),
+                }
+            }
+            400..=499 => self.done(
+                Availability::Unavailable,
+                Some(response),
+                Robots::allow_all(),
+            ),
+            _ => self.unreachable(Some(response)),
+        }
+    }
+
+    /// Drives the machine to the end with a closure which makes the requests.
+    pub fn run<F>(mut self, mut fetch: F) -> FetchResult
+    where
+        F: FnMut(&Url) -> FetchOutcome,
+    {
+        let mut step = self.start();
+        loop {
+            match step {
+                Step::Fetch(url) => step = self.next(fetch(&url)),
+                Step::Done(result) => return result,
+            }
+        }
+    }
+
+    fn unreachable(&self, response: Option<Response>) -> Step {
+        let robots = match self.unreachable_for >= self.unreachable_limit {
+            true => Robots::allow_all(),
+            false => Robots::disallow_all(),
+        };
+        self.done(Availability::Unreachable, response, robots)
+    }
+
+    fn done(
+        &self,
+        availability: Availability,
+        response: Option<Response>,
+        robots: Robots<'static>,
+    ) -> Step {
+        let (status, headers) = match response {
+            Some(response) => (Some(response.status), response.headers),
+            None => (None, Vec::new()),
+        };
+        Step::Done(FetchResult {
+            availability,
+            status,
+            url: self.url.clone(),
+            headers,
+            robots,
+            diagnostic: None,
+            source_hash: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{matcher::SimpleMatcher, payload::PayloadKind};
+
+    fn url() -> Url {
+        "https://example.com/robots.txt".parse().unwrap()
+    }
+
+    fn allowed(result: &FetchResult, path: &str) -> bool {
+        SimpleMatcher::new(&result.robots.choose_section("bot").rules).check_path(path)
+    }
+
+    #[test]
+    fn success() {
+        let result = FetchMachine::new(url()).run(|_| {
+            FetchOutcome::Response(Response::new(200).body("User-agent: *\nDisallow: /private\n"))
+        });
+        assert_eq!(Availability::Available, result.availability);
+        assert_eq!(Some(200), result.status);
+        assert!(allowed(&result, "/public"));
+        assert!(!allowed(&result, "/private"));
+        assert_eq!(None, result.diagnostic);
+        assert_eq!(
+            Some(body_hash(b"User-agent: *\nDisallow: /private\n")),
+            result.source_hash
+        );
+    }
+
+    #[test]
+    fn html_payload() {
+        let page = || {
+            FetchOutcome::Response(
+                Response::new(200)
+                    .with_header("Content-Type", "text/html")
+                    .body("<!doctype html>\n<html><body>Page not found</body></html>"),
+            )
+        };
+        let result = FetchMachine::new(url()).run(|_| page());
+        assert_eq!(Availability::Available, result.availability);
+        assert!(allowed(&result, "/private"));
+        assert_eq!(
+            Some(PayloadDiagnostic {
+                kind: PayloadKind::Html,
+                handling: PayloadHandling::AllowAll,
+            }),
+            result.diagnostic
+        );
+
+        let result = FetchMachine::new(url())
+            .payload_handling(PayloadHandling::DisallowAll)
+            .run(|_| page());
+        assert!(!allowed(&result, "/public"));
+        assert_eq!(
+            Some(PayloadHandling::DisallowAll),
+            result.diagnostic.map(|diagnostic| diagnostic.handling)
+        );
+    }
+
+    #[test]
+    fn client_error() {
+        let result = FetchMachine::new(url()).run(|_| FetchOutcome::Response(Response::new(404)));
+        assert_eq!(Availability::Unavailable, result.availability);
+        assert!(allowed(&result, "/private"));
+        assert_eq!(None, result.source_hash);
+    }
+
+    #[test]
+    fn server_error() {
+        let result = FetchMachine::new(url()).run(|_| FetchOutcome::Response(Response::new(503)));
+        assert_eq!(Availability::Unreachable, result.availability);
+        assert!(!allowed(&result, "/"));
+
+        // A `304` without a conditional request is no redirect.
+        let result = FetchMachine::new(url()).run(|_| FetchOutcome::Response(Response::new(304)));
+        assert_eq!(Availability::Unreachable, result.availability);
+        assert!(!allowed(&result, "/"));
+
+        let result = FetchMachine::new(url())
+            .run(|_| FetchOutcome::NetworkError("connection refused".into()));
+        assert_eq!(Availability::Unreachable, result.availability);
+        assert_eq!(None, result.status);
+        assert!(!allowed(&result, "/"));
+
+        let result = FetchMachine::new(url())
+            .unreachable_for(UNREACHABLE_LIMIT)
+            .run(|_| FetchOutcome::Response(Response::new(500)));
+        assert_eq!(Availability::Unreachable, result.availability);
+        assert!(allowed(&result, "/"));
+    }
+
+    #[test]
+    fn redirects() {
+        let mut requested = Vec::new();
+        let result = FetchMachine::new(url()).run(|url| {
+            requested.push(url.to_string());
+            match url.as_str() {
+                "https://example.com/robots.txt" => {
+                    FetchOutcome::Response(Response::new(301).location("/a"))
+                }
+                "https://example.com/a" => FetchOutcome::Response(
+                    Response::new(302).location("https://www.example.com/robots.txt"),
+                ),
+                _ => FetchOutcome::Response(Response::new(200).body("User-agent: *\nDisallow: /")),
+            }
+        });
+        assert_eq!(
+            requested,
+            vec![
+                "https://example.com/robots.txt",
+                "https://example.com/a",
+                "https://www.example.com/robots.txt",
+            ]
+        );
+        assert_eq!(Availability::Available, result.availability);
+        assert_eq!("https://www.example.com/robots.txt", result.url.as_str());
+        assert!(!allowed(&result, "/"));
+    }
+
+    #[test]
+    fn too_many_redirects() {
+        let mut count = 0;
+        let result = FetchMachine::new(url()).run(|_| {
+            count += 1;
+            FetchOutcome::Response(Response::new(301).location(format!("/r{}", count)))
+        });
+        assert_eq!(MAX_REDIRECTS + 1, count);
+        assert_eq!(Availability::Unavailable, result.availability);
+        assert!(allowed(&result, "/"));
+
+        let result = FetchMachine::new(url()).run(|_| FetchOutcome::Response(Response::new(301)));
+        assert_eq!(Availability::Unavailable, result.availability);
+    }
+}
@@ -0,0 +1,586 @@
+//! Indexing rules of a page, from `X-Robots-Tag` headers and robots `<meta>` tags,
+//! and the verdict they give together with robots.txt.
+//!
+//! ```
+//! use std::time::SystemTime;
+//! use robots_txt::{indexing::*, policy::Semantics, Robots};
+//!
+//! let robots = Robots::from_str_lossy("User-agent: *\nDisallow: /private\n");
+//! let page = PageDirectives::new()
+//!     .header("googlebot: nofollow")
+//!     .html(r#"<head><meta name="robots" content="noindex, max-snippet:50"></head>"#);
+//! let verdict = robots.verdict("Googlebot/2.1", "/post", &page, Semantics::rfc9309(), SystemTime::now());
+//! assert!(verdict.crawl && !verdict.index && !verdict.follow);
+//! assert_eq!(Some(50), verdict.indexing.max_snippet);
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use unicase::UniCase;
+
+use crate::{
+    parts::Robots,
+    policy::{product_token, Semantics},
+};
+
+/// How large an image preview may be shown, `max-image-preview`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ImagePreview {
+    None,
+    Standard,
+    Large,
+}
+
+/// A directive of an `X-Robots-Tag` header or a robots `<meta>` tag.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IndexDirective {
+    /// `all`, also `index` and `follow`: no restrictions.
+    All,
+    NoIndex,
+    NoFollow,
+    /// `none`: `noindex` and `nofollow`.
+    None,
+    NoSnippet,
+    /// `noarchive`, also `nocache`.
+    NoArchive,
+    NoTranslate,
+    NoImageIndex,
+    IndexIfEmbedded,
+    /// Characters of a text snippet, `-1` for no limit.
+    MaxSnippet(i64),
+    MaxImagePreview(ImagePreview),
+    /// Seconds of a video preview, `-1` for no limit.
+    MaxVideoPreview(i64),
+    /// The page is not to be shown in results after this moment.
+    UnavailableAfter(SystemTime),
+}
+
+impl IndexDirective {
+    /// Parses one directive, ignoring case, e.g. `noindex` or `max-snippet: 20`.
+    ///
+    /// `unavailable_after` takes RFC 822, RFC 850 and ISO 8601 dates.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (name, value) = match input.find(':') {
+            Some(pos) => (input[..pos].trim(), Some(input[pos + 1..].trim())),
+            None => (input, None),
+        };
+        let name = name.to_ascii_lowercase();
+        let directive = match (name.as_str(), value) {
+            ("all", None) | ("index", None) | ("follow", None) => IndexDirective::All,
+            ("noindex", None) => IndexDirective::NoIndex,
+            ("nofollow", None) => IndexDirective::NoFollow,
+            ("none", None) => IndexDirective::None,
+            ("nosnippet", None) => IndexDirective::NoSnippet,
+            ("noarchive", None) | ("nocache", None) => IndexDirective::NoArchive,
+            ("notranslate", None) => IndexDirective::NoTranslate,
+            ("noimageindex", None) => IndexDirective::NoImageIndex,
+            ("indexifembedded", None) => IndexDirective::IndexIfEmbedded,
+            ("max-snippet", Some(value)) => IndexDirective::MaxSnippet(parse_limit(value)?),
+            ("max-video-preview", Some(value)) => {
+                IndexDirective::MaxVideoPreview(parse_limit(value)?)
+            }
+            ("max-image-preview", Some(value)) => {
+                IndexDirective::MaxImagePreview(match value.to_ascii_lowercase().as_str() {
+                    "none" => ImagePreview::None,
+                    "standard" => ImagePreview::Standard,
+                    "large" => ImagePreview::Large,
+                    _ => return None,
+                })
+            }
+            ("unavailable_after", Some(value)) => {
+                IndexDirective::UnavailableAfter(parse_date(value)?)
+            }
+            _ => return None,
+        };
+        Some(directive)
+    }
+
+    fn is_name(name: &str) -> bool {
+        [
+            "max-snippet",
+            "max-image-preview",
+            "max-video-preview",
+            "unavailable_after",
+        ]
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+fn parse_limit(value: &str) -> Option<i64> {
+    value.parse().ok().filter(|&limit| limit >= -1)
+}
+
+/// A directive with the crawler it is for, `None` for all of them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScopedDirective {
+    pub agent: Option<String>,
+    pub directive: IndexDirective,
+}
+
+/// What a crawler may do with a page it fetched, the most restrictive of its directives.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Indexing {
+    pub index: bool,
+    pub follow: bool,
+    pub snippet: bool,
+    pub archive: bool,
+    pub translate: bool,
+    pub image_index: bool,
+    /// Whether the page may be indexed when embedded into another one despite `noindex`.
+    pub index_if_embedded: bool,
+    /// `None` for no limit.
+    pub max_snippet: Option<i64>,
+    pub max_image_preview: Option<ImagePreview>,
+    /// `None` for no limit.
+    pub max_video_preview: Option<i64>,
+    pub unavailable_after: Option<SystemTime>,
+}
+
+impl Default for Indexing {
+    fn default() -> Self {
+        Indexing {
+            index: true,
+            follow: true,
+            snippet: true,
+            archive: true,
+            translate: true,
+            image_index: true,
+            index_if_embedded: false,
+            max_snippet: None,
+            max_image_preview: None,
+            max_video_preview: None,
+            unavailable_after: None,
+        }
+    }
+}
+
+impl Indexing {
+    /// Applies a directive, keeping the more restrictive of conflicting values.
+    pub fn apply(&mut self, directive: IndexDirective) {
+        let min = |limit: Option<i64>, value: i64| match (limit, value) {
+            (limit, -1) => limit,
+            (Some(limit), value) => Some(limit.min(value)),
+            (None, value) => Some(value),
+        };
+        match directive {
+            IndexDirective::All => {}
+            IndexDirective::NoIndex => self.index = false,
+            IndexDirective::NoFollow => self.follow = false,
+            IndexDirective::None => {
+                self.index = false;
+                self.follow = false;
+            }
+            IndexDirective::NoSnippet => self.snippet = false,
+            IndexDirective::NoArchive => self.archive = false,
+            IndexDirective::NoTranslate => self.translate = false,
+            IndexDirective::NoImageIndex => self.image_index = false,
+            IndexDirective::IndexIfEmbedded => self.index_if_embedded = true,
+            IndexDirective::MaxSnippet(value) => self.max_snippet = min(self.max_snippet, value),
+            IndexDirective::MaxVideoPreview(value) => {
+                self.max_video_preview = min(self.max_video_preview, value)
+            }
+            IndexDirective::MaxImagePreview(value) => {
+                self.max_image_preview =
+                    Some(self.max_image_preview.map_or(value, |v| v.min(value)))
+            }
+            IndexDirective::UnavailableAfter(time) => {
+                self.unavailable_after = Some(self.unavailable_after.map_or(time, |t| t.min(time)))
+            }
+        }
+    }
+
+    /// Whether the page may be shown in results at this moment.
+    pub fn is_indexable_at(&self, now: SystemTime) -> bool {
+        self.index && self.unavailable_after.is_none_or(|after| now < after)
+    }
+}
+
+/// The `X-Robots-Tag` headers and robots `<meta>` tags of one page.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PageDirectives {
+    directives: Vec<ScopedDirective>,
+}
+
+impl PageDirectives {
+    pub fn new() -> Self {
+        PageDirectives::default()
+    }
+
+    pub fn directives(&self) -> &[ScopedDirective] {
+        &self.directives
+    }
+
+    /// Adds the value of an `X-Robots-Tag` header, e.g. `googlebot: noindex, nofollow`.
+    ///
+    /// A name followed by `:` which is not a directive sets the crawler
+    /// of the directives after it. Unknown directives are ignored.
+    pub fn header(mut self, value: &str) -> Self {
+        let mut agent: Option<String> = None;
+        let mut items = value.split(',').peekable();
+        while let Some(item) = items.next() {
+            let mut item = item.trim().to_string();
+            if let Some(pos) = item.find(':') {
+                let (name, rest) = (&item[..pos], item[pos + 1..].trim());
+                if !IndexDirective::is_name(name) && !name.trim().contains(char::is_whitespace) {
+                    agent = Some(name.trim().to_string());
+                    item = rest.to_string();
+                }
+            }
+            // RFC 850 dates start with the day of the week and a comma.
+            if let Some(pos) = item.find(':') {
+                let value = item[pos + 1..].trim();
+                if IndexDirective::is_name(&item[..pos])
+                    && !value.is_empty()
+                    && value.chars().all(|c| c.is_ascii_alphabetic())
+                {
+                    if let Some(next) = items.next() {
+                        item = format!("{},{}", item, next);
+                    }
+                }
+            }
+            if let Some(directive) = IndexDirective::parse(&item) {
+                self.directives.push(ScopedDirective {
+                    agent: agent.clone(),
+                    directive,
+                });
+            }
+        }
+        self
+    }
+
+    /// Adds the content of a `<meta>` tag, named `robots` for all crawlers or after one.
+    pub fn meta(mut self, name: &str, content: &str) -> Self {
+        let agent = match name.trim().eq_ignore_ascii_case("robots") {
+            true => None,
+            false => Some(name.trim().to_string()),
+        };
+        for item in content.split(',') {
+            if let Some(directive) = IndexDirective::parse(item) {
+                self.directives.push(ScopedDirective {
+                    agent: agent.clone(),
+                    directive,
+                });
+            }
+        }
+        self
+    }
+
+    /// Adds the robots `<meta>` tags of an HTML document.
+    pub fn html(mut self, html: &str) -> Self {
+        // ASCII lowercasing keeps the offsets of the original.
+        let lower = html.to_ascii_lowercase();
+        let mut pos = 0;
+        while let Some(found) = lower[pos..].find("<meta") {
+            let start = pos + found + "<meta".len();
+            let end = lower[start..]
+                .find('>')
+                .map_or(lower.len(), |end| start + end);
+            pos = end;
+            if !lower[start..]
+                .starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            {
+                continue;
+            }
+            let attributes = attributes(&html[start..end]);
+            let find = |name| {
+                attributes
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|&(_, value)| value)
+            };
+            if let (Some(name), Some(content)) = (find("name"), find("content")) {
+                self = self.meta(name, content);
+            }
+        }
+        self
+    }
+
+    /// The directives which apply to the crawler, the general ones and those for
+    /// its product token.
+    pub fn for_agent(&self, agent: &str) -> Indexing {
+        let token = UniCase::new(product_token(agent));
+        let mut indexing = Indexing::default();
+        self.directives
+            .iter()
+            .filter(|scoped| {
+                scoped
+                    .agent
+                    .as_ref()
+                    .is_none_or(|name| UniCase::new(name.as_str()) == token)
+            })
+            .for_each(|scoped| indexing.apply(scoped.directive));
+        indexing
+    }
+}
+
+/// What a crawler may do with a URL, by robots.txt and the page's own directives.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Verdict {
+    /// Whether robots.txt allows fetching the URL.
+    pub crawl: bool,
+    /// Whether the page's content may be indexed and shown at the moment.
+    ///
+    /// A page which may not be crawled can't be indexed, but its `noindex` can't be seen
+    /// either, so search engines may still list the bare URL found in links.
+    pub index: bool,
+    /// Whether the links of the page may be followed.
+    pub follow: bool,
+    /// The page's directives for the crawler, default if it may not be crawled.
+    pub indexing: Indexing,
+}
+
+impl<'a> Robots<'a> {
+    /// Merges robots.txt with the page's directives into the verdict for the crawler.
+    pub fn verdict(
+        &self,
+        agent: &str,
+        path: &str,
+        page: &PageDirectives,
+        semantics: Semantics,
+        now: SystemTime,
+    ) -> Verdict {
+        let crawl = self
+            .policy_for(agent, semantics.agents)
+            .check_path_with(path, semantics.rules);
+        let indexing = match crawl {
+            true => page.for_agent(agent),
+            false => Indexing::default(),
+        };
+        Verdict {
+            crawl,
+            index: crawl && indexing.is_indexable_at(now),
+            follow: crawl && indexing.follow,
+            indexing,
+        }
+    }
+}
+
+fn attributes(tag: &str) -> Vec<(&str, &str)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return attributes;
+        }
+        let name = &rest[..end];
+        rest = rest[end..].trim_start();
+        if !rest.starts_with('=') {
+            attributes.push((name, ""));
+            continue;
+        }
+        rest = rest[1..].trim_start();
+        let (value, next) = match rest.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => match rest[1..].find(quote) {
+                Some(end) => (&rest[1..end + 1], &rest[end + 2..]),
+                None => (&rest[1..], ""),
+            },
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        attributes.push((name, value));
+        rest = next;
+    }
+}
+
+/// Parses `25 Jun 2010 15:00:00 PST`, `Friday, 25-Jun-10 15:00:00 GMT`
+/// or `2010-06-25T15:00:00+01:00`.
+fn parse_date(input: &str) -> Option<SystemTime> {
+    let input = input.trim();
+    let (date, time, zone) = match input.as_bytes().get(4) == Some(&b'-') {
+        true => {
+            let date = input.get(..10)?;
+            let rest = input[10..].trim_start_matches(['T', 't', ' ']);
+            let split = rest.find(['Z', 'z', '+', '-']).unwrap_or(rest.len());
+            let (time, zone) = rest.split_at(split);
+            let mut parts = date.split('-');
+            let year: i64 = parts.next()?.parse().ok()?;
+            let month: i64 = parts.next()?.parse().ok()?;
+            let day: i64 = parts.next()?.parse().ok()?;
+            ((year, month, day), time.trim(), zone.trim())
+        }
+        false => {
+            let input = input.find(',').map_or(input, |pos| &input[pos + 1..]);
+            let mut words: Vec<&str> = input.split_whitespace().collect();
+            if words.first().is_some_and(|word| word.contains('-')) {
+                let date: Vec<_> = words.remove(0).split('-').collect();
+                words.splice(0..0, date);
+            }
+            let day: i64 = words.first()?.parse().ok()?;
+            let month = words.get(1)?.get(..3)?;
+            let month = [
+                "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+            ]
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(month))? as i64
+                + 1;
+            let year: i64 = match words.get(2)?.parse().ok()? {
+                year if year < 70 => 2000 + year,
+                year if year < 100 => 1900 + year,
+                year => year,
+            };
+            let time = words.get(3).copied().unwrap_or("");
+            let zone = words.get(4).copied().unwrap_or("");
+            ((year, month, day), time, zone)
+        }
+    };
+    let (year, month, day) = date;
+    if !(1..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut clock = time.split(':');
+    let mut field = |max: i64| -> Option<i64> {
+        match clock.next() {
+            Some(value) => value.parse().ok().filter(|v| (0..=max).contains(v)),
+            None => Some(0),
+        }
+    };
+    let seconds = match time.is_empty() {
+        true => 0,
+        false => field(23)? * 3600 + field(59)? * 60 + field(60)?,
+    };
+    let offset = match zone.to_ascii_uppercase().as_str() {
+        "" | "Z" | "GMT" | "UT" | "UTC" => 0,
+        "EDT" => -4,
+        "EST" | "CDT" => -5,
+        "CST" | "MDT" => -6,
+        "MST" | "PDT" => -7,
+        "PST" => -8,
+        zone => {
+            let sign = match zone.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let digits = zone[1..].replace(':', "");
+            if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let minutes: i64 =
+                digits[..2].parse::<i64>().ok()? * 60 + digits[2..].parse::<i64>().ok()?;
+            return at(
+                days_from_civil(year, month, day),
+                seconds - sign * minutes * 60,
+            );
+        }
+    };
+    at(days_from_civil(year, month, day), seconds - offset * 3600)
+}
+
+fn at(days: i64, seconds: i64) -> Option<SystemTime> {
+    let secs = days * 86_400 + seconds;
+    match secs >= 0 {
+        true => UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64)),
+        false => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs())),
+    }
+}
+
+/// Days since 1970-01-01 of a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn directives() {
+        assert_eq!(
+            Some(IndexDirective::MaxSnippet(20)),
+            IndexDirective::parse(" Max-Snippet : 20")
+        );
+        assert_eq!(
+            Some(IndexDirective::MaxImagePreview(ImagePreview::Standard)),
+            IndexDirective::parse("max-image-preview:standard")
+        );
+        assert_eq!(None, IndexDirective::parse("max-snippet:-2"));
+        assert_eq!(None, IndexDirective::parse("noindex:yes"));
+        assert_eq!(None, IndexDirective::parse("noodp"));
+
+        let june_25 = time(1_277_478_000);
+        for date in &[
+            "25 Jun 2010 15:00:00 GMT",
+            "Fri, 25 Jun 2010 08:00:00 PDT",
+            "Friday, 25-Jun-10 15:00:00 GMT",
+            "2010-06-25T16:00:00+01:00",
+            "2010-06-25T15:00:00Z",
+        ] {
+            assert_eq!(Some(june_25), parse_date(date), "{}", date);
+        }
+        assert_eq!(Some(time(1_277_424_000)), parse_date("2010-06-25"));
+        assert_eq!(None, parse_date("25 Jun 2010 15:00:00 XYZ"));
+        assert_eq!(None, parse_date("2010-13-25"));
+        assert_eq!(None, parse_date("25"));
+        assert_eq!(None, parse_date("2010-06-25T15:00:00+1\u{e9}1"));
+        assert_eq!(None, parse_date("25 Jun 99999999999999999 00:00:00 GMT"));
+        let page = PageDirectives::new()
+            .header("unavailable_after: 2010-06-25T15:00:00+1\u{e9}1")
+            .header("unavailable_after: 25 Jun 99999999999999999 00:00:00 GMT");
+        assert!(page.directives().is_empty());
+    }
+
+    #[test]
+    fn headers_and_meta() {
+        let page = PageDirectives::new()
+            .header("noarchive, max-snippet: 100")
+            .header("googlebot: noindex, nofollow, otherbot: max-snippet:-1")
+            .header("unavailable_after: Friday, 25-Jun-10 15:00:00 GMT")
+            .html(
+                r#"<html><head>
+<metadata name="robots" content="none">
+<META NAME='Googlebot-News' CONTENT='nosnippet'/>
+<meta content="max-snippet:50, max-image-preview:large" name=robots>
+<meta name="description" content="noindex is not for robots">
+</head></html>"#,
+            );
+
+        let google = page.for_agent("Googlebot/2.1");
+        assert!(!google.index && !google.follow && !google.archive && google.snippet);
+        assert_eq!(Some(50), google.max_snippet);
+        assert_eq!(Some(ImagePreview::Large), google.max_image_preview);
+        assert_eq!(Some(time(1_277_478_000)), google.unavailable_after);
+
+        let news = page.for_agent("Googlebot-News");
+        assert!(news.index && !news.snippet);
+
+        let other = page.for_agent("otherbot");
+        assert!(other.index && other.follow);
+        assert_eq!(Some(50), other.max_snippet);
+    }
+
+    #[test]
+    fn verdicts() {
+        let robots = Robots::from_str_lossy("User-agent: *\nDisallow: /private\n");
+        let page = PageDirectives::new().header("unavailable_after: 2010-06-25");
+        let verdict =
+            |path, now| robots.verdict("bot", path, &page, Semantics::rfc9309(), time(now));
+
+        let before = verdict("/news", 1_277_000_000);
+        assert!(before.crawl && before.index && before.follow);
+        let after = verdict("/news", 1_278_000_000);
+        assert!(after.crawl && !after.index && after.follow);
+        let private = verdict("/private/news", 1_277_000_000);
+        assert!(!private.crawl && !private.index && !private.follow);
+        assert_eq!(Indexing::default(), private.indexing);
+    }
+}
@@ -14,12 +14,30 @@
 //! * Allow
 //! * Crawl-delay
 //! * Request-rate
+//! * Visit-time
 //! * Sitemap
 //! * Host
 
+pub mod analysis;
 pub mod builder;
+pub mod cache;
+pub mod clock;
+pub mod compose;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod diff;
+pub mod fetch;
+pub mod indexing;
 pub mod matcher;
 pub mod parse;
 pub mod parts;
+pub mod payload;
+pub mod policy;
+pub mod presets;
+pub mod registry;
+pub mod render;
+pub mod schedule;
+pub mod store;
+pub mod template;
 
 pub use self::parts::Robots;
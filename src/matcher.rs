@@ -1,4 +1,4 @@
-use unicase::UniCase;
+use std::cmp::Reverse;
 
 use crate::parts::*;
 
@@ -17,7 +17,7 @@ impl<'a> SimpleMatcher<'a> {
                 break;
             }
             let rule: &Rule = rule;
-            match (rule.allow, rule.path.as_ref()) {
+            match (rule.allow, rule.path.as_str()) {
                 // FIXME this rule must be filtered in a section
                 (true, "") => continue,
                 (false, "") | (true, "/") => global_rule = Some(true),
@@ -38,6 +38,9 @@ impl<'a> SimpleMatcher<'a> {
         }
     }
 
+    /// Whether the first rule matching the path allows it.
+    ///
+    /// Paths are matched case-sensitively, with `*` and `$` as in RFC 9309.
     pub fn check_path(&self, path: &str) -> bool {
         match *self {
             SimpleMatcher::GlobalRule(rule) => rule,
@@ -47,11 +50,7 @@ impl<'a> SimpleMatcher<'a> {
                     if rule.path.is_empty() {
                         return true;
                     }
-                    if rule.path.len() > path.len() {
-                        continue;
-                    }
-                    let part: &str = &path[..rule.path.len()];
-                    if UniCase::new(part) == UniCase::new(&rule.path) {
+                    if rule.path.matches(path) {
                         return rule.allow;
                     }
                 }
@@ -61,9 +60,94 @@ impl<'a> SimpleMatcher<'a> {
     }
 
     pub fn has_rules(&self) -> bool {
-        match *self {
-            SimpleMatcher::Rules(_) => true,
-            _ => false,
+        matches!(*self, SimpleMatcher::Rules(_))
+    }
+}
+
+/// How the rules of a group decide a path.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum RuleMatching {
+    /// The first matching rule wins. An empty rule allows everything, unlike in the
+    /// `SimpleMatcher` shortcut for an empty `Allow` before `Disallow: /`.
+    #[default]
+    FirstMatch,
+    /// RFC 9309: the longest matching pattern wins, `Allow` wins a tie.
+    LongestMatch,
+}
+
+impl RuleMatching {
+    pub fn check_path(self, rules: &[Rule<'_>], path: &str) -> bool {
+        self.decide(rules.iter().map(|rule| (rule, rule.path.matches(path))))
+    }
+
+    /// Decides by which of the rules match, a path is allowed if none does.
+    pub(crate) fn decide<'r, 'a: 'r, I>(self, rules: I) -> bool
+    where
+        I: IntoIterator<Item = (&'r Rule<'a>, bool)>,
+    {
+        let rules: Vec<_> = rules.into_iter().collect();
+        match self.decider(rules.iter().cloned()) {
+            Some(index) => rules[index].0.allow || rules[index].0.path.is_empty(),
+            None => true,
+        }
+    }
+
+    /// The index of the rule which decides, by which of the rules match.
+    pub(crate) fn decider<'r, 'a: 'r, I>(self, rules: I) -> Option<usize>
+    where
+        I: IntoIterator<Item = (&'r Rule<'a>, bool)>,
+    {
+        let mut rules = rules.into_iter().enumerate();
+        match self {
+            // An empty rule stops the search, allowing everything.
+            RuleMatching::FirstMatch => rules
+                .find(|(_, (rule, matches))| rule.path.is_empty() || *matches)
+                .map(|(index, _)| index),
+            RuleMatching::LongestMatch => rules
+                .filter(|(_, (_, matches))| *matches)
+                .map(|(index, (rule, _))| (rule.path.specificity(), rule.allow, Reverse(index)))
+                .max()
+                .map(|(_, _, Reverse(index))| index),
+        }
+    }
+}
+
+/// Owns a parsed robots.txt and checks paths for any user agent.
+///
+/// Groups which allow or disallow everything are resolved once, on creation.
+#[derive(Clone, Debug)]
+pub struct RobotsMatcher {
+    robots: Robots<'static>,
+    /// Global rules of `robots.sections`, followed by the one of the default group.
+    globals: Vec<Option<bool>>,
+}
+
+impl RobotsMatcher {
+    pub fn new(robots: Robots<'static>) -> Self {
+        let globals = robots
+            .sections
+            .iter()
+            .chain(Some(&robots.default_section))
+            .map(|section| match SimpleMatcher::new(&section.rules) {
+                SimpleMatcher::GlobalRule(rule) => Some(rule),
+                SimpleMatcher::Rules(_) => None,
+            })
+            .collect();
+        RobotsMatcher { robots, globals }
+    }
+
+    pub fn robots(&self) -> &Robots<'static> {
+        &self.robots
+    }
+
+    pub fn check_path(&self, ua: &str, path: &str) -> bool {
+        let (index, section) = match self.robots.choose_section_index(ua) {
+            Some(index) => (index, &self.robots.sections[index]),
+            None => (self.robots.sections.len(), &self.robots.default_section),
+        };
+        match self.globals[index] {
+            Some(rule) => rule,
+            None => SimpleMatcher::Rules(&section.rules).check_path(path),
         }
     }
 }
@@ -97,6 +181,58 @@ Disallow:
         // FIXME striped trailing "/"
         // assert!(!matcher.check_path("/tmp"));
         assert!(!matcher.check_path("/tmp/file1"));
+        // Paths are case-sensitive.
+        assert!(matcher.check_path("/Tmp/file1"));
+        assert!(matcher.check_path("/CYBERWORLD/map/"));
+    }
+
+    #[test]
+    fn robots_matcher() {
+        let matcher = RobotsMatcher::new(Robots::from_str_lossy(ROBOTS2).into_owned());
+        assert!(matcher.check_path("AnyBot", "/some/page"));
+        assert!(!matcher.check_path("AnyBot", "/cyberworld/map/object.html"));
+        assert!(matcher.check_path("CyberMapper/3.14", "/cyberworld/map/object.html"));
+    }
+
+    #[test]
+    fn matcher_wildcards() {
+        let robots = Robots::from_str_lossy(
+            r#"
+User-Agent: *
+Allow: /*.php$
+Disallow: /*.php
+Disallow: /private*/
+"#,
+        );
+        let matcher = SimpleMatcher::new(&robots.choose_section("").rules);
+        assert!(matcher.check_path("/index.php"));
+        assert!(!matcher.check_path("/index.php?page=1"));
+        assert!(!matcher.check_path("/private-area/file"));
+        assert!(matcher.check_path("/private"));
+    }
+
+    #[test]
+    fn rule_matching() {
+        let robots = Robots::from_str_lossy(
+            r#"
+User-Agent: *
+Disallow: /shop
+Allow: /shop/public
+"#,
+        );
+        let rules = &robots.default_section.rules;
+        assert!(!RuleMatching::FirstMatch.check_path(rules, "/shop/public/item"));
+        assert!(RuleMatching::LongestMatch.check_path(rules, "/shop/public/item"));
+        assert!(!RuleMatching::LongestMatch.check_path(rules, "/shop/cart"));
+        assert!(RuleMatching::LongestMatch.check_path(rules, "/about"));
+
+        let tie = [Rule::disallow("/page"), Rule::allow("/page")];
+        assert!(!RuleMatching::FirstMatch.check_path(&tie, "/page"));
+        assert!(RuleMatching::LongestMatch.check_path(&tie, "/page"));
+
+        let global = [Rule::allow(""), Rule::disallow("/")];
+        assert!(RuleMatching::FirstMatch.check_path(&global, "/page"));
+        assert!(!SimpleMatcher::new(&global).check_path("/page"));
     }
 
     #[test]
@@ -8,11 +8,11 @@ pub fn split_comment(input: &str) -> (&str, Option<&str>) {
 pub fn split_kv(input: &str) -> Option<(&str, &str)> {
     input
         .find(':')
-        .map(|pos| ((&input[..pos]).trim(), (&input[pos + 1..]).trim()))
+        .map(|pos| (input[..pos].trim(), input[pos + 1..].trim()))
 }
 
 pub fn split_rr(input: &str) -> Option<(&str, &str)> {
     input
         .find('/')
-        .map(|pos| ((&input[..pos]).trim(), (&input[pos + 1..]).trim()))
+        .map(|pos| (input[..pos].trim(), input[pos + 1..].trim()))
 }
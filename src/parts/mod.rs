@@ -1,6 +1,11 @@
+pub mod path_pattern;
 pub mod request_rate;
 pub mod robots;
 pub mod rule;
 pub mod section;
+pub mod usage;
+pub mod visit_time;
 
-pub use self::{request_rate::*, robots::*, rule::*, section::*};
+pub use self::{
+    path_pattern::*, request_rate::*, robots::*, rule::*, section::*, usage::*, visit_time::*,
+};
@@ -0,0 +1,214 @@
+use std::{borrow::Cow, error::Error, fmt, ops::Deref};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PatternError {
+    /// A non-empty pattern must start with `/` or `*`.
+    NotAbsolute,
+    /// Whitespace, control characters and `#` can't be written on a robots.txt line.
+    InvalidChar(char),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::NotAbsolute => f.write_str("must start with `/` or `*`"),
+            PatternError::InvalidChar(c) => write!(f, "invalid character {:?}", c),
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+/// A path pattern of an `Allow` or `Disallow` rule.
+///
+/// Follows [RFC 9309](https://www.rfc-editor.org/rfc/rfc9309.html#section-2.2.3):
+/// `*` matches any sequence of characters, a trailing `$` matches the end of the path,
+/// anything else is matched literally and case-sensitively against the beginning of the path.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PathPattern<'a>(Cow<'a, str>);
+
+impl<'a> PathPattern<'a> {
+    /// Wraps the pattern as is, without validation.
+    pub fn new<P>(pattern: P) -> Self
+    where
+        P: Into<Cow<'a, str>>,
+    {
+        PathPattern(pattern.into())
+    }
+
+    pub fn parse<P>(pattern: P) -> Result<Self, PatternError>
+    where
+        P: Into<Cow<'a, str>>,
+    {
+        let pattern = PathPattern::new(pattern);
+        pattern.validate().map(|()| pattern)
+    }
+
+    pub fn validate(&self) -> Result<(), PatternError> {
+        if let Some(c) = self
+            .0
+            .chars()
+            .find(|&c| c.is_whitespace() || c.is_control() || c == '#')
+        {
+            return Err(PatternError::InvalidChar(c));
+        }
+        if !self.0.is_empty() && !self.0.starts_with('/') && !self.0.starts_with('*') {
+            return Err(PatternError::NotAbsolute);
+        }
+        Ok(())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The length of the pattern in octets, the longest matching pattern wins.
+    pub fn specificity(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn has_wildcard(&self) -> bool {
+        self.0.contains('*')
+    }
+
+    pub fn has_end_anchor(&self) -> bool {
+        self.0.ends_with('$')
+    }
+
+    /// Checks the pattern against a path. An empty pattern matches nothing.
+    pub fn matches(&self, path: &str) -> bool {
+        if self.0.is_empty() {
+            return false;
+        }
+        let (pattern, anchored) = match self.has_end_anchor() {
+            true => (&self.0[..self.0.len() - 1], true),
+            false => (&self.0[..], false),
+        };
+        let path = path.as_bytes();
+
+        // Positions in the path reachable after the consumed part of the pattern, ascending.
+        let mut positions = vec![0];
+        for &c in pattern.as_bytes() {
+            if c == b'*' {
+                let first = positions[0];
+                positions = (first..=path.len()).collect();
+                continue;
+            }
+            positions = positions
+                .into_iter()
+                .filter(|&pos| pos < path.len() && path[pos] == c)
+                .map(|pos| pos + 1)
+                .collect();
+            if positions.is_empty() {
+                return false;
+            }
+        }
+        !anchored || positions.last() == Some(&path.len())
+    }
+
+    pub fn into_owned(self) -> PathPattern<'static> {
+        PathPattern(Cow::Owned(self.0.into_owned()))
+    }
+}
+
+impl<'a> Deref for PathPattern<'a> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> AsRef<str> for PathPattern<'a> {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> From<&'a str> for PathPattern<'a> {
+    fn from(pattern: &'a str) -> Self {
+        PathPattern::new(pattern)
+    }
+}
+
+impl<'a> From<String> for PathPattern<'a> {
+    fn from(pattern: String) -> Self {
+        PathPattern::new(pattern)
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for PathPattern<'a> {
+    fn from(pattern: Cow<'a, str>) -> Self {
+        PathPattern::new(pattern)
+    }
+}
+
+impl<'a> PartialEq<str> for PathPattern<'a> {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl<'a, 'b> PartialEq<&'b str> for PathPattern<'a> {
+    fn eq(&self, other: &&'b str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl<'a> fmt::Display for PathPattern<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert!(PathPattern::parse("").is_ok());
+        assert!(PathPattern::parse("/").is_ok());
+        assert!(PathPattern::parse("*.php$").is_ok());
+        assert_eq!(
+            PathPattern::parse("private"),
+            Err(PatternError::NotAbsolute)
+        );
+        assert_eq!(
+            PathPattern::parse("/a b"),
+            Err(PatternError::InvalidChar(' '))
+        );
+    }
+
+    #[test]
+    fn properties() {
+        let pattern = PathPattern::new("/fish*.php$");
+        assert_eq!(11, pattern.specificity());
+        assert!(pattern.has_wildcard());
+        assert!(pattern.has_end_anchor());
+        assert_eq!("/fish*.php$", pattern.to_string());
+        assert!(!PathPattern::new("/fish").has_wildcard());
+    }
+
+    #[test]
+    fn matches() {
+        let test = |pattern: &str, path: &str| PathPattern::new(pattern).matches(path);
+
+        assert!(!test("", "/"));
+        assert!(test("/", "/anything"));
+        assert!(test("/fish", "/fish.html"));
+        assert!(test("/fish", "/fishheads/yummy.html"));
+        assert!(!test("/fish", "/Fish.asp"));
+        assert!(!test("/fish", "/catfish"));
+        assert!(test("/fish*", "/fish"));
+        assert!(test("/*.php", "/folder/filename.php?parameters"));
+        assert!(!test("/*.php", "/windows.PHP"));
+        assert!(test("/*.php$", "/filename.php"));
+        assert!(!test("/*.php$", "/filename.php?parameters"));
+        assert!(!test("/*.php$", "/filename.php5"));
+        assert!(test("/fish*.php", "/fishheads/catfish.php?parameters"));
+        assert!(test("/$", "/"));
+        assert!(!test("/$", "/page"));
+        assert!(test("*", ""));
+    }
+}
@@ -1,26 +1,26 @@
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, fmt, io};
 
 use unicase::UniCase;
 
-use crate::{builder::*, parse::*, parts::*};
+use crate::{
+    builder::*,
+    parse::*,
+    parts::*,
+    render::{IoAdapter, RenderOptions, Renderer},
+};
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Robots<'a> {
     pub default_section: Section<'a>,
     pub sections: Vec<Section<'a>>,
     pub host: Option<Cow<'a, str>>,
+    /// Comment lines written at the top of the file.
+    pub comments: Vec<Cow<'a, str>>,
 }
 
 impl<'a> fmt::Display for Robots<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for section in &self.sections {
-            section.fmt(f)?;
-        }
-        self.default_section.fmt(f)?;
-        if let Some(host) = self.host.as_ref() {
-            writeln!(f, "Host: {}", host)?;
-        }
-        Ok(())
+        self.render_fmt(f, &RenderOptions::default())
     }
 }
 
@@ -29,6 +29,27 @@ impl<'a> Robots<'a> {
         RobotsBuilder::new()
     }
 
+    /// A policy which allows everything, as if there were no robots.txt at all.
+    pub fn allow_all() -> Robots<'a> {
+        Robots::default()
+    }
+
+    /// A policy which disallows everything.
+    pub fn disallow_all() -> Robots<'a> {
+        let mut robots = Robots::default();
+        robots.default_section.rules = vec![Rule::disallow("/")];
+        robots
+    }
+
+    pub fn into_owned(self) -> Robots<'static> {
+        Robots {
+            default_section: self.default_section.into_owned(),
+            sections: self.sections.into_iter().map(Section::into_owned).collect(),
+            host: self.host.map(|host| Cow::Owned(host.into_owned())),
+            comments: into_owned_all(self.comments),
+        }
+    }
+
     pub fn from_str_lossy(input: &'a str) -> Robots<'a> {
         let mut robots = Constructor::default();
 
@@ -73,6 +94,25 @@ impl<'a> Robots<'a> {
                             }
                         }
 
+                        k if k == UniCase::new("visit-time") => {
+                            if let Some(visit_time) = VisitTime::parse(v) {
+                                robots.section.visit_time = Some(visit_time);
+                            }
+                        }
+
+                        k if k == UniCase::new("content-signal") => {
+                            let directive = UsageDirective::ContentSignal;
+                            if let Some(pref) = UsagePreference::parse(directive, v) {
+                                robots.section.usage.push(pref);
+                            }
+                        }
+                        k if k == UniCase::new("content-usage") => {
+                            let directive = UsageDirective::ContentUsage;
+                            if let Some(pref) = UsagePreference::parse(directive, v) {
+                                robots.section.usage.push(pref);
+                            }
+                        }
+
                         // "Unrecognised headers are ignored"
                         _ => {}
                     }
@@ -83,29 +123,186 @@ impl<'a> Robots<'a> {
         robots.build()
     }
 
+    pub fn render_with(&self, options: &RenderOptions) -> String {
+        let mut out = String::new();
+        self.render_fmt(&mut out, options)
+            .expect("a Display implementation returned an error unexpectedly");
+        out
+    }
+
+    pub fn render_fmt<W>(&self, out: &mut W, options: &RenderOptions) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        Renderer::new(out, options).robots(self)
+    }
+
+    pub fn render_io<W>(&self, out: &mut W, options: &RenderOptions) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let mut adapter = IoAdapter {
+            inner: out,
+            error: None,
+        };
+        match self.render_fmt(&mut adapter, options) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+
     pub fn choose_section<U>(&self, ua: U) -> &Section<'a>
     where
         U: AsRef<str>,
     {
-        let ua: &str = ua.as_ref();
-        if !ua.is_empty() {
-            for section in &self.sections {
-                for ua2 in &section.useragents {
-                    if ua2.len() > ua.len() {
-                        continue;
-                    }
-                    let matches = (0..=ua.len() - ua2.len())
-                        .map(|i| &ua[i..i + ua2.len()])
-                        .any(|s: &str| UniCase::new(s) == UniCase::new(ua2));
+        match self.choose_section_index(ua.as_ref()) {
+            Some(index) => &self.sections[index],
+            None => &self.default_section,
+        }
+    }
 
-                    if matches {
-                        return section;
-                    }
-                }
+    /// The index of the group `choose_section` returns, `None` for the default group.
+    pub(crate) fn choose_section_index(&self, ua: &str) -> Option<usize> {
+        if ua.is_empty() {
+            return None;
+        }
+        self.sections.iter().position(|section| {
+            section
+                .useragents
+                .iter()
+                .any(|ua2| contains_ignore_case(ua, ua2))
+        })
+    }
+
+    /// Whether the usage preferences of the group `choose_section` returns permit
+    /// the use of the path, `None` if they say nothing about it.
+    pub fn permits_usage<U>(&self, ua: U, path: &str, usage: Usage) -> Option<bool>
+    where
+        U: AsRef<str>,
+    {
+        self.choose_section(ua).permits_usage(path, usage)
+    }
+
+    /// Returns the group which lists exactly this user agent, ignoring case.
+    pub fn find_group<U>(&self, ua: U) -> Option<&Section<'a>>
+    where
+        U: AsRef<str>,
+    {
+        let ua = ua.as_ref();
+        match ua == "*" {
+            true => Some(&self.default_section),
+            false => self.group_position(ua).map(|i| &self.sections[i]),
+        }
+    }
+
+    /// Returns the group which lists this user agent, creating a new one if there is none.
+    ///
+    /// The returned group may be shared with other user agents, see `split_agent`
+    /// for changes which must affect only one of them.
+    pub fn group_mut<U>(&mut self, ua: U) -> &mut Section<'a>
+    where
+        U: Into<Cow<'a, str>>,
+    {
+        let ua = ua.into();
+        if ua == "*" {
+            return &mut self.default_section;
+        }
+        let index = match self.group_position(&ua) {
+            Some(index) => index,
+            None => {
+                let mut section = Section::empty();
+                section.push_ua(ua);
+                self.sections.push(section);
+                self.sections.len() - 1
             }
+        };
+        &mut self.sections[index]
+    }
+
+    /// Returns a group which lists only this user agent.
+    ///
+    /// If the agent shares a group with others, it is moved into a copy of that group
+    /// placed right after the original one.
+    pub fn split_agent<U>(&mut self, ua: U) -> &mut Section<'a>
+    where
+        U: Into<Cow<'a, str>>,
+    {
+        let ua = ua.into();
+        let index = match self.group_position(&ua) {
+            Some(index) if self.sections[index].useragents.len() > 1 => index,
+            _ => return self.group_mut(ua),
+        };
+        let section = &mut self.sections[index];
+        let own = section
+            .useragents
+            .iter()
+            .find(|ua2| UniCase::new(ua2.as_ref()) == UniCase::new(ua.as_ref()))
+            .cloned()
+            .expect("agent is listed in the group");
+        section.useragents.remove(&own);
+        let mut split = section.clone();
+        split.useragents.clear();
+        split.useragents.insert(own);
+        split.comments.clear();
+        self.sections.insert(index + 1, split);
+        &mut self.sections[index + 1]
+    }
+
+    /// Removes the user agent from its group, dropping the group if no agents are left.
+    ///
+    /// Removing `*` resets the default group to allow everything.
+    pub fn remove_agent<U>(&mut self, ua: U) -> bool
+    where
+        U: AsRef<str>,
+    {
+        let ua = ua.as_ref();
+        if ua == "*" {
+            self.default_section = Section::default();
+            return true;
+        }
+        let index = match self.group_position(ua) {
+            Some(index) => index,
+            None => return false,
+        };
+        let section = &mut self.sections[index];
+        section
+            .useragents
+            .retain(|ua2| UniCase::new(ua2.as_ref()) != UniCase::new(ua));
+        if section.useragents.is_empty() {
+            self.sections.remove(index);
         }
-        &self.default_section
+        true
     }
+
+    /// Sets `Crawl-delay` for this user agent only.
+    pub fn set_crawl_delay<U>(&mut self, ua: U, delay: Option<f64>)
+    where
+        U: Into<Cow<'a, str>>,
+    {
+        self.split_agent(ua).crawl_delay = delay;
+    }
+
+    pub fn to_builder(&self) -> RobotsBuilder<'a> {
+        RobotsBuilder::from(self.clone())
+    }
+
+    fn group_position(&self, ua: &str) -> Option<usize> {
+        self.sections.iter().position(|section| {
+            section
+                .useragents
+                .iter()
+                .any(|ua2| UniCase::new(ua2.as_ref()) == UniCase::new(ua))
+        })
+    }
+}
+
+pub(crate) fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    needle.len() <= haystack.len()
+        && (0..=haystack.len() - needle.len())
+            .filter_map(|i| haystack.get(i..i + needle.len()))
+            .any(|s| UniCase::new(s) == UniCase::new(needle))
 }
 
 struct Constructor<'a> {
@@ -158,6 +355,7 @@ impl<'a> Constructor<'a> {
             default_section: self.default_section.unwrap_or_default(),
             sections: self.sections,
             host: self.host,
+            comments: Vec::new(),
         }
     }
 }
@@ -251,4 +449,140 @@ Host: example.com
         test(ROBOTS3, RESULT3);
         test(ROBOTS4, RESULT4);
     }
+
+    #[test]
+    fn edit() {
+        let mut robots = Robots::from_str_lossy(
+            r#"
+User-Agent: a-bot
+User-Agent: b-bot
+Disallow: /private
+Crawl-delay: 1
+
+User-Agent: *
+Disallow: /tmp/
+"#,
+        );
+
+        robots.set_crawl_delay("B-Bot", Some(5.));
+        robots.group_mut("c-bot").push_rule(Rule::disallow("/"));
+        robots.group_mut("*").push_rule(Rule::allow("/tmp/public"));
+        assert!(robots
+            .group_mut("a-bot")
+            .replace_rule(false, "/private", Rule::disallow("/secret"))
+            .is_some());
+        assert!(robots.find_group("b-bot").unwrap().rules[0].path == "/private");
+
+        assert_eq!(
+            robots.to_string(),
+            "\
+User-agent: a-bot
+Disallow: /secret
+Crawl-delay: 1
+
+User-agent: b-bot
+Disallow: /private
+Crawl-delay: 5
+
+User-agent: c-bot
+Disallow: /
+
+User-agent: *
+Disallow: /tmp/
+Allow: /tmp/public
+
+"
+        );
+
+        assert!(robots.remove_agent("c-bot"));
+        assert!(!robots.remove_agent("c-bot"));
+        assert!(robots.find_group("c-bot").is_none());
+        assert!(robots
+            .group_mut("a-bot")
+            .remove_rule(false, "/secret")
+            .is_some());
+        assert!(robots.group_mut("a-bot").rules.is_empty());
+    }
+
+    #[test]
+    fn to_builder() {
+        let robots = Robots::from_str_lossy(ROBOTS4);
+        let rebuilt = robots
+            .to_builder()
+            .start_section("bot")
+            .disallow("/")
+            .end_section()
+            .build();
+        assert_eq!(rebuilt.default_section, robots.default_section);
+        assert_eq!(rebuilt.host, robots.host);
+        assert_eq!(rebuilt.sections.len(), 1);
+    }
+
+    #[test]
+    fn usage_preferences() {
+        let robots = Robots::from_str_lossy(
+            r#"
+User-agent: GPTBot
+Content-Usage: train-ai=n
+Content-Usage: /docs/ train-ai=y
+Disallow: /private
+
+User-agent: *
+content-signal: search=yes, ai-train=no # managed
+Content-Signal: garbage
+Allow: /
+"#,
+        );
+        assert_eq!(
+            Some(false),
+            robots.permits_usage("GPTBot/1.2", "/", Usage::GenAiTraining)
+        );
+        assert_eq!(
+            Some(true),
+            robots.permits_usage("GPTBot/1.2", "/docs/a", Usage::AiTraining)
+        );
+        assert_eq!(None, robots.permits_usage("GPTBot", "/", Usage::Search));
+        assert_eq!(
+            Some(true),
+            robots.permits_usage("other", "/", Usage::Search)
+        );
+        assert_eq!(None, robots.permits_usage("other", "/", Usage::AiInput));
+
+        assert_eq!(
+            robots.to_string(),
+            "\
+User-agent: GPTBot
+Disallow: /private
+Content-Usage: train-ai=n
+Content-Usage: /docs/ train-ai=y
+
+User-agent: *
+Allow: /
+Content-Signal: search=yes, ai-train=no
+
+"
+        );
+
+        let built = Robots::builder()
+            .start_section("GPTBot")
+            .disallow("/private")
+            .usage(
+                UsagePreference::new(UsageDirective::ContentUsage).usage(Usage::AiTraining, false),
+            )
+            .usage(
+                UsagePreference::new(UsageDirective::ContentUsage)
+                    .path("/docs/")
+                    .signal("train-ai", true),
+            )
+            .end_section()
+            .with_section("*", |section| {
+                section.allow("/").usage(
+                    UsagePreference::new(UsageDirective::ContentSignal)
+                        .usage(Usage::Search, true)
+                        .usage(Usage::AiTraining, false),
+                )
+            })
+            .build();
+        assert_eq!(built.to_string(), robots.to_string());
+    }
 }
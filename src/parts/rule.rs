@@ -1,45 +1,71 @@
 use std::{borrow::Cow, fmt};
 
+use crate::{
+    parts::PathPattern,
+    render::{RenderOptions, Renderer},
+};
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Rule<'a> {
     pub allow: bool,
-    pub path: Cow<'a, str>,
+    pub path: PathPattern<'a>,
+    /// Comment lines written before the rule.
+    pub comments: Vec<Cow<'a, str>>,
+    /// A comment written at the end of the rule line.
+    pub trailing_comment: Option<Cow<'a, str>>,
 }
 
 impl<'a> Rule<'a> {
     pub fn new<P>(allow: bool, path: P) -> Self
     where
-        P: Into<Cow<'a, str>>,
+        P: Into<PathPattern<'a>>,
     {
         let path = path.into();
-        Rule { allow, path }
+        Rule {
+            allow,
+            path,
+            comments: Vec::new(),
+            trailing_comment: None,
+        }
     }
 
     pub fn allow<P>(path: P) -> Self
     where
-        P: Into<Cow<'a, str>>,
+        P: Into<PathPattern<'a>>,
     {
         Rule::new(true, path)
     }
 
     pub fn disallow<P>(path: P) -> Self
     where
-        P: Into<Cow<'a, str>>,
+        P: Into<PathPattern<'a>>,
     {
         Rule::new(false, path)
     }
+
+    pub fn into_owned(self) -> Rule<'static> {
+        Rule {
+            allow: self.allow,
+            path: self.path.into_owned(),
+            comments: into_owned_all(self.comments),
+            trailing_comment: self.trailing_comment.map(|c| Cow::Owned(c.into_owned())),
+        }
+    }
+}
+
+pub(crate) fn into_owned_all<'a, C>(items: C) -> Vec<Cow<'static, str>>
+where
+    C: IntoIterator<Item = Cow<'a, str>>,
+{
+    items
+        .into_iter()
+        .map(|c| Cow::Owned(c.into_owned()))
+        .collect()
 }
 
 impl<'a> fmt::Display for Rule<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(match self.allow {
-            true => "Allow:",
-            false => "Disallow:",
-        })?;
-        if !self.path.is_empty() {
-            write!(f, " {}", self.path)?;
-        };
-        writeln!(f)
+        Renderer::new(f, &RenderOptions::default()).rule(self)
     }
 }
 
@@ -51,5 +77,14 @@ mod tests {
     fn render() {
         assert_eq!("Allow: /\n", Rule::allow("/").to_string());
         assert_eq!("Disallow: /\n", Rule::disallow("/").to_string());
+        assert_eq!("Disallow:\n", Rule::disallow("").to_string());
+
+        let mut rule = Rule::disallow("/tmp/");
+        rule.comments.push("Scratch space".into());
+        rule.trailing_comment = Some("these will soon disappear".into());
+        assert_eq!(
+            "# Scratch space\nDisallow: /tmp/ # these will soon disappear\n",
+            rule.to_string()
+        );
     }
 }
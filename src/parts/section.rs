@@ -2,15 +2,23 @@ use std::{borrow::Cow, collections::BTreeSet, fmt, iter::FromIterator};
 
 use url::{ParseError as UrlParseError, Url};
 
-use crate::parts::*;
+use crate::{
+    parts::*,
+    render::{RenderOptions, Renderer},
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Section<'a> {
     pub crawl_delay: Option<f64>,
     pub req_rate: Option<RequestRate>,
+    pub visit_time: Option<VisitTime>,
     pub rules: Vec<Rule<'a>>,
+    /// `Content-Signal` and `Content-Usage` lines.
+    pub usage: Vec<UsagePreference<'a>>,
     pub sitemaps: BTreeSet<Url>,
     pub useragents: BTreeSet<Cow<'a, str>>,
+    /// Comment lines written before the group.
+    pub comments: Vec<Cow<'a, str>>,
 }
 
 impl<'a> Default for Section<'a> {
@@ -18,30 +26,19 @@ impl<'a> Default for Section<'a> {
         Section {
             crawl_delay: None,
             req_rate: None,
+            visit_time: None,
             rules: vec![Rule::disallow("")],
+            usage: Vec::new(),
             sitemaps: BTreeSet::new(),
-            useragents: BTreeSet::from_iter(Some(Cow::from("*")).into_iter()),
+            useragents: BTreeSet::from_iter(Some(Cow::from("*"))),
+            comments: Vec::new(),
         }
     }
 }
 
 impl<'a> fmt::Display for Section<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for ua in &self.useragents {
-            writeln!(f, "User-agent: {}", ua)?;
-        }
-        for rule in &self.rules {
-            rule.fmt(f)?;
-        }
-        if let Some(delay) = self.crawl_delay.as_ref() {
-            writeln!(f, "Crawl-delay: {}", delay)?;
-        }
-        if let Some(rate) = self.req_rate.as_ref() {
-            rate.fmt(f)?;
-        }
-        for url in &self.sitemaps {
-            writeln!(f, "Sitemap: {}", url)?;
-        }
+        Renderer::new(&mut *f, &RenderOptions::default()).section(self)?;
         writeln!(f)
     }
 }
@@ -51,24 +48,50 @@ impl<'a> Section<'a> {
         Section {
             crawl_delay: None,
             req_rate: None,
+            visit_time: None,
             rules: Vec::new(),
+            usage: Vec::new(),
             sitemaps: BTreeSet::new(),
             useragents: BTreeSet::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    pub fn into_owned(self) -> Section<'static> {
+        Section {
+            crawl_delay: self.crawl_delay,
+            req_rate: self.req_rate,
+            visit_time: self.visit_time,
+            rules: self.rules.into_iter().map(Rule::into_owned).collect(),
+            usage: self
+                .usage
+                .into_iter()
+                .map(UsagePreference::into_owned)
+                .collect(),
+            sitemaps: self.sitemaps,
+            useragents: into_owned_all(self.useragents).into_iter().collect(),
+            comments: into_owned_all(self.comments),
         }
     }
 
+    /// Whether there is nothing to render, empty groups are left out of a robots.txt.
     pub fn is_empty(&self) -> bool {
-        self.crawl_delay.is_none()
+        self.comments.is_empty()
+            && self.crawl_delay.is_none()
             && self.req_rate.is_none()
+            && self.visit_time.is_none()
             && self.rules.is_empty()
+            && self.usage.is_empty()
             && self.sitemaps.is_empty()
             && self.useragents.is_empty()
     }
 
     pub fn has_rules(&self) -> bool {
         !self.rules.is_empty()
+            || !self.usage.is_empty()
             || self.crawl_delay.is_some()
             || self.req_rate.is_some()
+            || self.visit_time.is_some()
             || !self.sitemaps.is_empty()
     }
 
@@ -84,14 +107,19 @@ impl<'a> Section<'a> {
                 self.useragents.append(&mut other.useragents);
             }
         }
+        self.comments.append(&mut other.comments);
         self.sitemaps.append(&mut other.sitemaps);
         self.rules.append(&mut other.rules);
+        self.usage.append(&mut other.usage);
         if other.crawl_delay.is_some() {
             self.crawl_delay = other.crawl_delay;
         }
         if other.req_rate.is_some() {
             self.req_rate = other.req_rate;
         }
+        if other.visit_time.is_some() {
+            self.visit_time = other.visit_time;
+        }
     }
 
     pub fn push_ua<U>(&mut self, ua: U)
@@ -112,6 +140,29 @@ impl<'a> Section<'a> {
         self.rules.push(rule)
     }
 
+    /// Whether the group's usage preferences permit the use of this path, see `permits_usage`.
+    pub fn permits_usage(&self, path: &str, usage: Usage) -> Option<bool> {
+        permits_usage(&self.usage, path, usage)
+    }
+
+    /// Removes the first rule with this kind and path.
+    pub fn remove_rule(&mut self, allow: bool, path: &str) -> Option<Rule<'a>> {
+        self.rule_position(allow, path)
+            .map(|index| self.rules.remove(index))
+    }
+
+    /// Replaces the first rule with this kind and path, returning the old one.
+    pub fn replace_rule(&mut self, allow: bool, path: &str, rule: Rule<'a>) -> Option<Rule<'a>> {
+        self.rule_position(allow, path)
+            .map(|index| ::std::mem::replace(&mut self.rules[index], rule))
+    }
+
+    fn rule_position(&self, allow: bool, path: &str) -> Option<usize> {
+        self.rules
+            .iter()
+            .position(|rule| rule.allow == allow && rule.path == path)
+    }
+
     pub fn push_sitemap(&mut self, url: &str) -> Result<(), UrlParseError> {
         Url::parse(url).map(|url| {
             self.sitemaps.insert(url);
@@ -149,5 +200,17 @@ mod tests {
         let section = Section::default();
         assert!(!section.is_empty());
         assert!(section.is_default());
+
+        let mut section = Section::empty();
+        section.comments.push("Nothing yet".into());
+        assert!(!section.is_empty());
+        let robots = Robots::builder()
+            .section(section)
+            .with_section("*", |section| section.disallow("/private"))
+            .build();
+        assert_eq!(
+            "# Nothing yet\n\nUser-agent: *\nDisallow: /private\n\n",
+            robots.to_string()
+        );
     }
 }
@@ -0,0 +1,282 @@
+use std::{borrow::Cow, fmt};
+
+use crate::parts::*;
+
+/// The directive a usage preference was written with.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum UsageDirective {
+    /// `Content-Signal: search=yes, ai-train=no`, with the `search`, `ai-input`
+    /// and `ai-train` terms.
+    ContentSignal,
+    /// `Content-Usage: train-ai=n`, the IETF AI preferences vocabulary with the
+    /// `bots`, `train-ai`, `train-genai`, `ai-use` and `search` terms.
+    ContentUsage,
+}
+
+/// A use of the content, to ask whether it is permitted.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Usage {
+    /// Any automated processing.
+    Automated,
+    /// Building a search index and showing results linking to the content.
+    Search,
+    /// Training or fine-tuning AI models.
+    AiTraining,
+    /// Training generative AI models.
+    GenAiTraining,
+    /// Using the content as input to an AI model, e.g. to ground an answer.
+    AiInput,
+}
+
+impl Usage {
+    /// The terms which express this use, the most specific first.
+    ///
+    /// `Content-Usage` terms fall back to the broader ones, e.g. `train-genai` to `train-ai`
+    /// and `bots`. `Content-Signal` has no term for any automated processing.
+    pub fn terms(self, directive: UsageDirective) -> &'static [&'static str] {
+        match directive {
+            UsageDirective::ContentSignal => match self {
+                Usage::Automated => &[],
+                Usage::Search => &["search"],
+                Usage::AiTraining | Usage::GenAiTraining => &["ai-train"],
+                Usage::AiInput => &["ai-input"],
+            },
+            UsageDirective::ContentUsage => match self {
+                Usage::Automated => &["bots"],
+                Usage::Search => &["search", "bots"],
+                Usage::AiTraining => &["train-ai", "bots"],
+                Usage::GenAiTraining => &["train-genai", "train-ai", "bots"],
+                Usage::AiInput => &["ai-use", "bots"],
+            },
+        }
+    }
+}
+
+/// A `Content-Signal` or `Content-Usage` line of a group, optionally for paths only.
+///
+/// Terms this crate doesn't know are kept, so they are written back as they were.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct UsagePreference<'a> {
+    pub directive: UsageDirective,
+    /// The paths the preference applies to, all of them if `None`.
+    pub path: Option<PathPattern<'a>>,
+    /// Terms and whether the use is permitted, in their order in the line.
+    pub signals: Vec<(Cow<'a, str>, bool)>,
+}
+
+impl<'a> UsagePreference<'a> {
+    pub fn new(directive: UsageDirective) -> Self {
+        UsagePreference {
+            directive,
+            path: None,
+            signals: Vec::new(),
+        }
+    }
+
+    /// Parses the value of the directive, e.g. `/blog/ search=yes, ai-train=no`.
+    ///
+    /// Entries with values other than `yes`, `no`, `y` or `n` are skipped,
+    /// `None` is returned if no entry is left.
+    pub fn parse(directive: UsageDirective, input: &'a str) -> Option<Self> {
+        let input = input.trim();
+        let (path, signals) = match input.starts_with('/') || input.starts_with('*') {
+            true => match input.find(char::is_whitespace) {
+                Some(pos) => (Some(PathPattern::new(&input[..pos])), &input[pos..]),
+                None => return None,
+            },
+            false => (None, input),
+        };
+        let signals: Vec<_> = signals
+            .split(',')
+            .filter_map(|entry| {
+                let entry = entry.split(';').next().unwrap_or_default();
+                let mut kv = entry.splitn(2, '=');
+                let term = kv.next()?.trim();
+                let allowed = match kv.next()?.trim().to_ascii_lowercase().as_str() {
+                    "yes" | "y" => true,
+                    "no" | "n" => false,
+                    _ => return None,
+                };
+                match term.is_empty() {
+                    true => None,
+                    false => Some((Cow::Borrowed(term), allowed)),
+                }
+            })
+            .collect();
+        match signals.is_empty() {
+            true => None,
+            false => Some(UsagePreference {
+                directive,
+                path,
+                signals,
+            }),
+        }
+    }
+
+    /// Limits the preference to the paths matching the pattern.
+    pub fn path<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathPattern<'a>>,
+    {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Sets a term, replacing its previous value.
+    pub fn signal<T>(mut self, term: T, allowed: bool) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        let term = term.into();
+        match self.signals.iter_mut().find(|(other, _)| *other == term) {
+            Some(signal) => signal.1 = allowed,
+            None => self.signals.push((term, allowed)),
+        }
+        self
+    }
+
+    /// Sets the most specific term for the use, or every term of `Content-Signal`
+    /// for `Usage::Automated`, which it has no own term for.
+    pub fn usage(self, usage: Usage, allowed: bool) -> Self {
+        match (self.directive, usage) {
+            (UsageDirective::ContentSignal, Usage::Automated) => {
+                [Usage::Search, Usage::AiInput, Usage::AiTraining]
+                    .iter()
+                    .fold(self, |pref, &usage| pref.usage(usage, allowed))
+            }
+            (directive, usage) => self.signal(usage.terms(directive)[0], allowed),
+        }
+    }
+
+    /// Whether the paths of the preference include this one.
+    pub fn applies_to(&self, path: &str) -> bool {
+        self.path
+            .as_ref()
+            .is_none_or(|pattern| pattern.matches(path))
+    }
+
+    /// Whether the preference permits the use, `None` if it says nothing about it.
+    pub fn permits(&self, usage: Usage) -> Option<bool> {
+        usage.terms(self.directive).iter().find_map(|term| {
+            self.signals
+                .iter()
+                .find(|(other, _)| other.eq_ignore_ascii_case(term))
+                .map(|&(_, allowed)| allowed)
+        })
+    }
+
+    pub fn into_owned(self) -> UsagePreference<'static> {
+        UsagePreference {
+            directive: self.directive,
+            path: self.path.map(PathPattern::into_owned),
+            signals: self
+                .signals
+                .into_iter()
+                .map(|(term, allowed)| (Cow::Owned(term.into_owned()), allowed))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> fmt::Display for UsagePreference<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = self.path.as_ref() {
+            write!(f, "{} ", path)?;
+        }
+        for (i, (term, allowed)) in self.signals.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            let value = match (self.directive, allowed) {
+                (UsageDirective::ContentSignal, true) => "yes",
+                (UsageDirective::ContentSignal, false) => "no",
+                (UsageDirective::ContentUsage, true) => "y",
+                (UsageDirective::ContentUsage, false) => "n",
+            };
+            write!(f, "{}={}", term, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolves a use for a path among the preferences of a group.
+///
+/// The preference with the most specific path which says anything about the use wins,
+/// one without a path being the least specific. Of equally specific ones a refusal wins.
+pub fn permits_usage<'p, 'a: 'p, I>(preferences: I, path: &str, usage: Usage) -> Option<bool>
+where
+    I: IntoIterator<Item = &'p UsagePreference<'a>>,
+{
+    preferences
+        .into_iter()
+        .filter(|pref| pref.applies_to(path))
+        .filter_map(|pref| {
+            let specificity = pref.path.as_ref().map(|path| path.specificity() + 1);
+            pref.permits(usage)
+                .map(|allowed| (specificity.unwrap_or(0), !allowed))
+        })
+        .max()
+        .map(|(_, refused)| !refused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let pref = UsagePreference::parse(
+            UsageDirective::ContentSignal,
+            " search=yes, ai-train=no, ai-input=maybe, future=yes",
+        )
+        .unwrap();
+        assert_eq!(None, pref.path);
+        assert_eq!("search=yes, ai-train=no, future=yes", pref.to_string());
+        assert_eq!(Some(true), pref.permits(Usage::Search));
+        assert_eq!(Some(false), pref.permits(Usage::GenAiTraining));
+        assert_eq!(None, pref.permits(Usage::AiInput));
+        assert_eq!(None, pref.permits(Usage::Automated));
+
+        let pref =
+            UsagePreference::parse(UsageDirective::ContentUsage, "/blog/ train-ai=n, bots=y")
+                .unwrap();
+        assert_eq!("/blog/ train-ai=n, bots=y", pref.to_string());
+        assert!(pref.applies_to("/blog/post") && !pref.applies_to("/shop"));
+        assert_eq!(Some(false), pref.permits(Usage::GenAiTraining));
+        assert_eq!(Some(true), pref.permits(Usage::Search));
+
+        assert_eq!(
+            None,
+            UsagePreference::parse(UsageDirective::ContentUsage, "/blog/")
+        );
+        assert_eq!(
+            None,
+            UsagePreference::parse(UsageDirective::ContentUsage, "train-ai")
+        );
+
+        let pref = UsagePreference::new(UsageDirective::ContentSignal)
+            .usage(Usage::Automated, true)
+            .usage(Usage::GenAiTraining, false);
+        assert_eq!("search=yes, ai-input=yes, ai-train=no", pref.to_string());
+    }
+
+    #[test]
+    fn resolve() {
+        let prefs = vec![
+            UsagePreference::new(UsageDirective::ContentSignal)
+                .usage(Usage::Search, true)
+                .usage(Usage::AiTraining, false),
+            UsagePreference::new(UsageDirective::ContentUsage).signal("train-ai", true),
+            UsagePreference::new(UsageDirective::ContentUsage)
+                .path("/open/")
+                .signal("train-ai", true),
+        ];
+        assert_eq!(Some(false), permits_usage(&prefs, "/", Usage::AiTraining));
+        assert_eq!(
+            Some(true),
+            permits_usage(&prefs, "/open/a", Usage::AiTraining)
+        );
+        assert_eq!(Some(true), permits_usage(&prefs, "/", Usage::Search));
+        assert_eq!(None, permits_usage(&prefs, "/", Usage::AiInput));
+    }
+}
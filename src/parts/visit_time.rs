@@ -0,0 +1,151 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const DAY: u64 = 24 * 60 * 60;
+const DAY_MINUTES: u16 = 24 * 60;
+
+/// The time of day, in UTC, when a crawler may visit, e.g. `Visit-time: 0600-0845`.
+///
+/// A window whose end is before its start crosses midnight.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct VisitTime {
+    /// Minutes since midnight.
+    pub start: u16,
+    /// Minutes since midnight, exclusive, up to `1440` for the end of the day.
+    pub end: u16,
+}
+
+impl VisitTime {
+    /// A window in minutes since midnight, a start past the day is clamped to its last minute
+    /// and an end past the day to its end.
+    pub fn new(start: u16, end: u16) -> Self {
+        VisitTime {
+            start: start.min(DAY_MINUTES - 1),
+            end: end.min(DAY_MINUTES),
+        }
+    }
+
+    /// Parses `hhmm-hhmm`, also accepting `hh:mm-hh:mm`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.splitn(2, '-');
+        let start = parse_hhmm(parts.next()?)? % DAY_MINUTES;
+        let end = parse_hhmm(parts.next()?)?;
+        Some(VisitTime { start, end })
+    }
+
+    /// Whether the minute of the day falls into the window.
+    pub fn contains(&self, minute: u16) -> bool {
+        match self.start <= self.end {
+            true => self.start <= minute && minute < self.end,
+            false => self.start <= minute || minute < self.end,
+        }
+    }
+
+    /// How long from this moment until the window opens, zero if it is open.
+    pub fn wait_from(&self, now: SystemTime) -> Duration {
+        let since_midnight = match now.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => since_epoch.as_secs() % DAY,
+            Err(_) => return Duration::from_secs(0),
+        };
+        if self.start == self.end || self.contains((since_midnight / 60) as u16) {
+            return Duration::from_secs(0);
+        }
+        let start = u64::from(self.start) * 60;
+        let wait = (start + DAY - since_midnight) % DAY;
+        let subsec = now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| Duration::from_nanos(u64::from(d.subsec_nanos())))
+            .unwrap_or_default();
+        Duration::from_secs(wait).saturating_sub(subsec)
+    }
+}
+
+impl fmt::Display for VisitTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}{:02}-{:02}{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+fn parse_hhmm(input: &str) -> Option<u16> {
+    let input = input.trim();
+    let (hours, minutes) = match input.find(':') {
+        Some(pos) => (&input[..pos], &input[pos + 1..]),
+        None if input.len() == 4 && input.is_char_boundary(2) => (&input[..2], &input[2..]),
+        None => return None,
+    };
+    let hours: u16 = hours.parse().ok()?;
+    let minutes: u16 = minutes.parse().ok()?;
+    match hours < 24 && minutes < 60 {
+        true => Some(hours * 60 + minutes),
+        // `2400` is a common way to write the end of the day.
+        false if hours == 24 && minutes == 0 => Some(DAY_MINUTES),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Some(VisitTime::new(360, 525)),
+            VisitTime::parse("0600-0845")
+        );
+        assert_eq!(
+            Some(VisitTime::new(1320, 120)),
+            VisitTime::parse("22:00 - 02:00")
+        );
+        assert_eq!(None, VisitTime::parse("0600"));
+        assert_eq!(None, VisitTime::parse("2500-0100"));
+        assert_eq!(Some(VisitTime::new(0, 1440)), VisitTime::parse("0000-2400"));
+        assert_eq!(Some(VisitTime::new(0, 60)), VisitTime::parse("2400-0100"));
+        assert_eq!("2200-2400", VisitTime::new(1320, 1440).to_string());
+        assert_eq!("0600-0845", VisitTime::new(360, 525).to_string());
+    }
+
+    #[test]
+    fn window() {
+        let at = |h: u64, m: u64| UNIX_EPOCH + Duration::from_secs(DAY * 100 + h * 3600 + m * 60);
+        let morning = VisitTime::new(360, 525);
+        assert_eq!(Duration::from_secs(0), morning.wait_from(at(7, 0)));
+        assert_eq!(Duration::from_secs(3600), morning.wait_from(at(5, 0)));
+        assert_eq!(
+            Duration::from_secs(DAY - 3 * 3600),
+            morning.wait_from(at(9, 0))
+        );
+
+        let night = VisitTime::new(1320, 120);
+        assert!(night.contains(0));
+        assert!(!night.contains(600));
+        assert_eq!(Duration::from_secs(0), night.wait_from(at(23, 30)));
+        assert_eq!(Duration::from_secs(19 * 3600), night.wait_from(at(3, 0)));
+
+        assert_eq!(VisitTime::new(1439, 1440), VisitTime::new(1500, 2000));
+        let day = VisitTime::new(0, 1440);
+        assert!(day.contains(0));
+        assert!(day.contains(1439));
+        assert_eq!(Duration::from_secs(0), day.wait_from(at(23, 59)));
+        let evening = VisitTime::new(1320, 1440);
+        assert!(evening.contains(1439));
+        assert!(!evening.contains(0));
+        assert_eq!(Duration::from_secs(3600), evening.wait_from(at(21, 0)));
+        // A window set past the day doesn't make the wait negative.
+        let past = VisitTime {
+            start: 1500,
+            end: 10,
+        };
+        let now = at(1, 0) + Duration::from_millis(500);
+        assert_eq!(Duration::from_secs(0), past.wait_from(now));
+    }
+}
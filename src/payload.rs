@@ -0,0 +1,256 @@
+//! Recognizing response bodies which are not robots.txt, like an HTML error page
+//! served with status 200.
+//!
+//! ```
+//! use robots_txt::{payload::*, Robots};
+//!
+//! let body = b"<!DOCTYPE html><html><body>Not found</body></html>";
+//! let (robots, diagnostic) =
+//!     Robots::from_bytes(body, Some("text/html"), PayloadHandling::DisallowAll);
+//! assert_eq!(Robots::disallow_all(), robots);
+//! assert_eq!(PayloadKind::Html, diagnostic.unwrap().kind);
+//! ```
+
+use std::{error::Error, fmt};
+
+use crate::parts::Robots;
+
+/// How many leading bytes are inspected.
+const SNIFF_LEN: usize = 1024;
+
+/// What a robots.txt body looks like.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PayloadKind {
+    /// Plain text, parsed as robots.txt.
+    Text,
+    /// An HTML page, typically an error page or a single page application.
+    Html,
+    /// A JSON document, typically an API error.
+    Json,
+    /// Compressed, image or other binary data.
+    Binary,
+}
+
+impl PayloadKind {
+    /// Inspects the start of a body, with the `Content-Type` of the response as a hint.
+    ///
+    /// Text starting with `<` or `{` and `[` is never a robots.txt line, text mentioning
+    /// `<html` without any `User-agent` line is taken for HTML as well. Other text is only
+    /// taken for what the `Content-Type` says if it has no robots.txt line, as many servers
+    /// send valid files with a wrong one.
+    pub fn classify(body: &[u8], content_type: Option<&str>) -> PayloadKind {
+        let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+        let head = &body[..body.len().min(SNIFF_LEN)];
+        let magic: &[&[u8]] = &[b"\x1F\x8B", b"\x89PNG", b"%PDF-", b"PK\x03\x04", b"GIF8"];
+        let controls = head
+            .iter()
+            .filter(|&&b| b < 0x20 && !b"\t\n\x0C\r".contains(&b))
+            .count();
+        if head.contains(&0)
+            || magic.iter().any(|m| head.starts_with(m))
+            || controls * 10 > head.len()
+        {
+            return PayloadKind::Binary;
+        }
+
+        let text = String::from_utf8_lossy(head).to_ascii_lowercase();
+        match text.trim_start().chars().next() {
+            Some('<') => PayloadKind::Html,
+            Some('{') | Some('[') => PayloadKind::Json,
+            _ if (text.contains("<html") || text.contains("<!doctype html"))
+                && !text.contains("user-agent") =>
+            {
+                PayloadKind::Html
+            }
+            _ if text.lines().any(is_robots_line) => PayloadKind::Text,
+            _ => content_type
+                .and_then(PayloadKind::from_content_type)
+                .unwrap_or(PayloadKind::Text),
+        }
+    }
+
+    /// The kind a `Content-Type` announces, `None` for text and unknown types.
+    fn from_content_type(content_type: &str) -> Option<PayloadKind> {
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        match media_type.as_str() {
+            "text/html" | "application/xhtml+xml" => Some(PayloadKind::Html),
+            "application/json" => Some(PayloadKind::Json),
+            media_type if media_type.ends_with("+json") => Some(PayloadKind::Json),
+            "application/octet-stream"
+            | "application/pdf"
+            | "application/zip"
+            | "application/gzip" => Some(PayloadKind::Binary),
+            media_type
+                if ["image/", "audio/", "video/", "font/"]
+                    .iter()
+                    .any(|prefix| media_type.starts_with(prefix)) =>
+            {
+                Some(PayloadKind::Binary)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether a lowercase line starts with a directive every robots.txt has.
+fn is_robots_line(line: &str) -> bool {
+    let name = line.split(':').next().unwrap_or_default().trim();
+    line.contains(':') && ["user-agent", "allow", "disallow", "sitemap"].contains(&name)
+}
+
+impl fmt::Display for PayloadKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PayloadKind::Text => "text",
+            PayloadKind::Html => "HTML",
+            PayloadKind::Json => "JSON",
+            PayloadKind::Binary => "binary data",
+        })
+    }
+}
+
+/// The policy applied when the body is not text.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PayloadHandling {
+    /// Allow everything, as if there were no robots.txt.
+    #[default]
+    AllowAll,
+    /// Disallow everything until the file is fixed.
+    DisallowAll,
+    /// Parse the body anyway, which finds no rules in most cases.
+    Parse,
+}
+
+impl fmt::Display for PayloadHandling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PayloadHandling::AllowAll => "allowing everything",
+            PayloadHandling::DisallowAll => "disallowing everything",
+            PayloadHandling::Parse => "parsing it anyway",
+        })
+    }
+}
+
+/// A robots.txt body which is not text, and what was done about it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PayloadDiagnostic {
+    pub kind: PayloadKind,
+    pub handling: PayloadHandling,
+}
+
+impl fmt::Display for PayloadDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "robots.txt looks like {} rather than text, {}",
+            self.kind, self.handling
+        )
+    }
+}
+
+impl Error for PayloadDiagnostic {}
+
+impl Robots<'static> {
+    /// Parses a body of unknown content, see `PayloadKind::classify`.
+    ///
+    /// A body which is not text is handled as asked and reported.
+    pub fn from_bytes(
+        body: &[u8],
+        content_type: Option<&str>,
+        handling: PayloadHandling,
+    ) -> (Robots<'static>, Option<PayloadDiagnostic>) {
+        let kind = PayloadKind::classify(body, content_type);
+        let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+        let parse = || Robots::from_str_lossy(&String::from_utf8_lossy(body)).into_owned();
+        if kind == PayloadKind::Text {
+            return (parse(), None);
+        }
+        let robots = match handling {
+            PayloadHandling::AllowAll => Robots::allow_all(),
+            PayloadHandling::DisallowAll => Robots::disallow_all(),
+            PayloadHandling::Parse => parse(),
+        };
+        (robots, Some(PayloadDiagnostic { kind, handling }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        let kind = |body: &[u8]| PayloadKind::classify(body, None);
+        assert_eq!(PayloadKind::Text, kind(b""));
+        assert_eq!(
+            PayloadKind::Text,
+            kind(b"\xEF\xBB\xBFUser-agent: *\nDisallow: /\n")
+        );
+        assert_eq!(
+            PayloadKind::Text,
+            kind(b"# <html> in a comment\nUser-agent: *\nDisallow:\n")
+        );
+        assert_eq!(PayloadKind::Text, kind("Disallow: /caf\u{e9}\n".as_bytes()));
+        assert_eq!(
+            PayloadKind::Html,
+            kind(b"\n  <!DOCTYPE html>\n<html></html>")
+        );
+        assert_eq!(PayloadKind::Html, kind(b"<div id=\"app\"></div>"));
+        assert_eq!(
+            PayloadKind::Html,
+            kind(b"Warning: include(): failed\n<html><body>Error</body></html>")
+        );
+        assert_eq!(PayloadKind::Json, kind(b"{\"error\": \"not found\"}"));
+        assert_eq!(PayloadKind::Json, kind(b" [1, 2]"));
+        assert_eq!(PayloadKind::Binary, kind(b"\x1F\x8B\x08\x00\x00\x00"));
+        assert_eq!(PayloadKind::Binary, kind(b"User-agent: *\0\0"));
+        assert_eq!(PayloadKind::Binary, kind(b"\x01\x02\x03abc"));
+    }
+
+    #[test]
+    fn content_type() {
+        let kind = |body: &[u8], content_type| PayloadKind::classify(body, Some(content_type));
+        assert_eq!(
+            PayloadKind::Html,
+            kind(b"Not found", "text/html; charset=utf-8")
+        );
+        assert_eq!(PayloadKind::Json, kind(b"null", "application/problem+json"));
+        assert_eq!(PayloadKind::Binary, kind(b"abc", "image/png"));
+        assert_eq!(PayloadKind::Text, kind(b"Not found", "text/plain"));
+        // The body wins over a wrong type either way.
+        assert_eq!(
+            PayloadKind::Text,
+            kind(b"User-agent: *\nDisallow: /private\n", "text/html")
+        );
+        assert_eq!(PayloadKind::Html, kind(b"<html></html>", "text/plain"));
+    }
+
+    #[test]
+    fn from_bytes() {
+        let html = b"<html><body><pre>User-agent: *\nDisallow: /</pre></body></html>";
+        let (robots, diagnostic) = Robots::from_bytes(html, None, PayloadHandling::AllowAll);
+        assert_eq!(Robots::allow_all(), robots);
+        assert_eq!(
+            "robots.txt looks like HTML rather than text, allowing everything",
+            diagnostic.unwrap().to_string()
+        );
+
+        let (robots, diagnostic) = Robots::from_bytes(html, None, PayloadHandling::Parse);
+        assert_eq!(1, robots.default_section.rules.len());
+        assert_eq!(PayloadHandling::Parse, diagnostic.unwrap().handling);
+
+        let text = b"\xEF\xBB\xBFUser-agent: *\nDisallow: /private\n";
+        let (robots, diagnostic) =
+            Robots::from_bytes(text, Some("text/html"), PayloadHandling::DisallowAll);
+        assert_eq!(
+            Robots::from_str_lossy("User-agent: *\nDisallow: /private\n"),
+            robots
+        );
+        assert_eq!(None, diagnostic);
+    }
+}
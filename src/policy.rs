@@ -0,0 +1,311 @@
+use std::{borrow::Cow, collections::BTreeSet, fmt};
+
+use unicase::UniCase;
+use url::Url;
+
+use crate::{
+    matcher::{RuleMatching, SimpleMatcher},
+    parts::{robots::contains_ignore_case, *},
+};
+
+/// How a crawler's name is matched against `User-agent` lines.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum AgentMatching {
+    /// The first group with a user agent contained in the crawler's name, ignoring case,
+    /// as `Robots::choose_section` does.
+    #[default]
+    Substring,
+    /// RFC 9309: every group whose user agent equals the crawler's product token,
+    /// ignoring case, e.g. `Googlebot` for `Googlebot/2.1`. The groups are merged.
+    ProductToken,
+}
+
+/// How a crawler picks its groups and how their rules decide a path.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Semantics {
+    pub agents: AgentMatching,
+    pub rules: RuleMatching,
+}
+
+impl Semantics {
+    /// What `Robots::choose_section` does, with the first matching rule deciding.
+    ///
+    /// `SimpleMatcher` may differ: it skips an empty `Allow` and lets a `Disallow: /` decide
+    /// the whole group, so `Allow:` then `Disallow: /` blocks everything there.
+    pub fn legacy() -> Self {
+        Semantics::default()
+    }
+
+    pub fn rfc9309() -> Self {
+        Semantics {
+            agents: AgentMatching::ProductToken,
+            rules: RuleMatching::LongestMatch,
+        }
+    }
+}
+
+/// Everything one crawler obeys in a robots.txt, with its groups merged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentPolicy<'a> {
+    /// The user agent lines which matched, `*` if only the default group applies.
+    pub useragents: BTreeSet<Cow<'a, str>>,
+    pub rules: Vec<Rule<'a>>,
+    pub crawl_delay: Option<f64>,
+    pub req_rate: Option<RequestRate>,
+    pub visit_time: Option<VisitTime>,
+    /// The `Content-Signal` and `Content-Usage` lines of the groups.
+    pub usage: Vec<UsagePreference<'a>>,
+    /// The sitemaps of the whole file, they don't belong to groups.
+    pub sitemaps: BTreeSet<Url>,
+    pub host: Option<Cow<'a, str>>,
+}
+
+impl<'a> AgentPolicy<'a> {
+    pub fn new(robots: &Robots<'a>, agent: &str, matching: AgentMatching) -> Self {
+        let mut groups: Vec<(&Section<'a>, Vec<&Cow<'a, str>>)> = Vec::new();
+        match matching {
+            AgentMatching::Substring => {
+                if let Some(index) = robots.choose_section_index(agent) {
+                    let section = &robots.sections[index];
+                    let matched = section
+                        .useragents
+                        .iter()
+                        .filter(|ua| contains_ignore_case(agent, ua))
+                        .collect();
+                    groups.push((section, matched));
+                }
+            }
+            AgentMatching::ProductToken => {
+                let token = product_token(agent);
+                for section in &robots.sections {
+                    let matched: Vec<_> = section
+                        .useragents
+                        .iter()
+                        .filter(|ua| {
+                            !token.is_empty()
+                                && UniCase::new(product_token(ua)) == UniCase::new(token)
+                        })
+                        .collect();
+                    if !matched.is_empty() {
+                        groups.push((section, matched));
+                    }
+                }
+            }
+        }
+        if groups.is_empty() {
+            groups.push((&robots.default_section, Vec::new()));
+        }
+
+        let mut policy = AgentPolicy {
+            useragents: BTreeSet::new(),
+            rules: Vec::new(),
+            crawl_delay: None,
+            req_rate: None,
+            visit_time: None,
+            usage: Vec::new(),
+            sitemaps: robots
+                .sections
+                .iter()
+                .chain(Some(&robots.default_section))
+                .flat_map(|section| section.sitemaps.iter().cloned())
+                .collect(),
+            host: robots.host.clone(),
+        };
+        for (section, matched) in groups {
+            policy.useragents.extend(matched.into_iter().cloned());
+            policy.rules.extend(section.rules.iter().cloned());
+            policy.usage.extend(section.usage.iter().cloned());
+            policy.crawl_delay = section.crawl_delay.or(policy.crawl_delay);
+            policy.req_rate = section.req_rate.or(policy.req_rate);
+            policy.visit_time = section.visit_time.or(policy.visit_time);
+        }
+        if policy.useragents.is_empty() {
+            policy.useragents.insert(Cow::Borrowed("*"));
+        }
+        policy
+    }
+
+    /// Whether only the default group applies.
+    pub fn is_default(&self) -> bool {
+        self.useragents.contains("*")
+    }
+
+    pub fn check_path(&self, path: &str) -> bool {
+        SimpleMatcher::new(&self.rules).check_path(path)
+    }
+
+    pub fn check_path_with(&self, path: &str, matching: RuleMatching) -> bool {
+        matching.check_path(&self.rules, path)
+    }
+
+    /// Whether the usage preferences permit the use of the path, `None` if they say nothing.
+    pub fn permits_usage(&self, path: &str, usage: Usage) -> Option<bool> {
+        permits_usage(&self.usage, path, usage)
+    }
+
+    /// A robots.txt with a single group, which this crawler reads the same way.
+    pub fn to_robots(&self) -> Robots<'a> {
+        let section = Section {
+            crawl_delay: self.crawl_delay,
+            req_rate: self.req_rate,
+            visit_time: self.visit_time,
+            rules: self.rules.clone(),
+            usage: self.usage.clone(),
+            sitemaps: self.sitemaps.clone(),
+            useragents: self.useragents.clone(),
+            comments: Vec::new(),
+        };
+        let (default_section, sections) = match self.is_default() {
+            true => (section, Vec::new()),
+            false => (Section::empty(), vec![section]),
+        };
+        Robots {
+            default_section,
+            sections,
+            host: self.host.clone(),
+            comments: Vec::new(),
+        }
+    }
+}
+
+impl<'a> fmt::Display for AgentPolicy<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_robots(), f)
+    }
+}
+
+impl<'a> Robots<'a> {
+    pub fn policy_for(&self, agent: &str, matching: AgentMatching) -> AgentPolicy<'a> {
+        AgentPolicy::new(self, agent, matching)
+    }
+
+    /// A minimal robots.txt with only what this agent obeys, its groups chosen by substring.
+    pub fn render_for(&self, agent: &str) -> String {
+        self.render_for_with(agent, AgentMatching::default())
+    }
+
+    /// `render_for` with the groups chosen as the crawler does.
+    pub fn render_for_with(&self, agent: &str, matching: AgentMatching) -> String {
+        self.policy_for(agent, matching).to_string()
+    }
+}
+
+/// The leading name of a user agent, e.g. `Googlebot` of `Googlebot/2.1`.
+pub fn product_token(agent: &str) -> &str {
+    let agent = agent.trim();
+    let end = agent
+        .find(|c: char| !(c.is_ascii_alphabetic() || c == '_' || c == '-'))
+        .unwrap_or(agent.len());
+    &agent[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ROBOTS: &str = r#"
+User-agent: *
+Disallow: /private
+Sitemap: http://example.com/sitemap.xml
+
+User-agent: bingbot
+User-agent: msnbot
+Disallow: /search
+Crawl-delay: 5
+
+User-agent: googlebot-news
+Disallow: /archive
+
+User-agent: BingBot
+Allow: /search/public
+Request-rate: 1/10
+
+Host: example.com
+"#;
+
+    #[test]
+    fn token() {
+        assert_eq!("Googlebot", product_token("Googlebot/2.1"));
+        assert_eq!(
+            "Mozilla",
+            product_token("Mozilla/5.0 (compatible; bingbot/2.0)")
+        );
+        assert_eq!("", product_token("2bot"));
+    }
+
+    #[test]
+    fn substring() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        let policy = robots.policy_for(
+            "Mozilla/5.0 (compatible; bingbot/2.0)",
+            AgentMatching::Substring,
+        );
+        assert_eq!(
+            vec!["bingbot"],
+            policy.useragents.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(1, policy.rules.len());
+        assert_eq!(Some(5.), policy.crawl_delay);
+        assert_eq!(1, policy.sitemaps.len());
+        assert!(!policy.check_path("/search/public"));
+        assert!(policy.check_path("/private"));
+
+        let policy = robots.policy_for("Slurp", AgentMatching::Substring);
+        assert!(policy.is_default());
+        assert!(!policy.check_path("/private"));
+    }
+
+    #[test]
+    fn product_token_merges_groups() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        let policy = robots.policy_for("bingbot/2.0", AgentMatching::ProductToken);
+        assert_eq!(2, policy.useragents.len());
+        assert_eq!(2, policy.rules.len());
+        assert_eq!(Some(5.), policy.crawl_delay);
+        assert_eq!(Some(RequestRate::new(1, 10)), policy.req_rate);
+
+        // `googlebot` is not the token of `googlebot-news`.
+        let policy = robots.policy_for("Googlebot", AgentMatching::ProductToken);
+        assert!(policy.is_default());
+    }
+
+    #[test]
+    fn render_for() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        assert_eq!(
+            robots.render_for("bingbot"),
+            "\
+User-agent: bingbot
+Disallow: /search
+Crawl-delay: 5
+Sitemap: http://example.com/sitemap.xml
+
+Host: example.com
+"
+        );
+        assert_eq!(
+            robots.render_for("Slurp"),
+            "\
+User-agent: *
+Disallow: /private
+Sitemap: http://example.com/sitemap.xml
+
+Host: example.com
+"
+        );
+        assert_eq!(
+            robots.render_for_with("bingbot/2.0", AgentMatching::ProductToken),
+            "\
+User-agent: BingBot
+User-agent: bingbot
+Disallow: /search
+Allow: /search/public
+Crawl-delay: 5
+Request-rate: 1/10
+Sitemap: http://example.com/sitemap.xml
+
+Host: example.com
+"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Ready-made policies built from the registry of known crawlers.
+//!
+//! ```
+//! use robots_txt::{presets::*, Robots};
+//!
+//! let agents = Registry::bundled().recategorize("Amazonbot", AgentCategory::SearchEngine);
+//! let robots = Robots::builder()
+//!     .preset_with(Preset::BlockAiTraining, &agents)
+//!     .with_section("*", |section| section.disallow("/private"))
+//!     .build();
+//! assert!(robots.to_string().contains("User-agent: GPTBot\n"));
+//! ```
+
+use std::fmt;
+
+use crate::builder::*;
+pub use crate::registry::{AgentCategory, Registry, VERSION};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Preset {
+    /// Disallows everything to AI training crawlers.
+    BlockAiTraining,
+    /// Disallows everything to AI assistants fetching pages for their users.
+    BlockAiAssistants,
+    /// Disallows everything to SEO tool crawlers.
+    BlockSeoTools,
+    /// Allows search engines and disallows everything to the others.
+    OnlySearchEngines,
+    /// Disallows everything to everyone, e.g. for a staging site.
+    DisallowAll,
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Preset::BlockAiTraining => "block AI training crawlers",
+            Preset::BlockAiAssistants => "block AI assistants",
+            Preset::BlockSeoTools => "block SEO tool crawlers",
+            Preset::OnlySearchEngines => "allow only search engines",
+            Preset::DisallowAll => "disallow all",
+        })
+    }
+}
+
+impl<'a> RobotsBuilder<'a> {
+    /// Adds the groups of a preset with the bundled registry.
+    pub fn preset(self, preset: Preset) -> Self {
+        self.preset_with(preset, &Registry::bundled())
+    }
+
+    /// Adds the groups of a preset, taking the agents from the registry.
+    ///
+    /// Disallowing everything to everyone merges into the group for `*`.
+    pub fn preset_with(self, preset: Preset, agents: &Registry) -> Self {
+        let comment = match agents.version.is_empty() {
+            true => format!("Preset: {}", preset),
+            false => format!("Preset: {} (agents {})", preset, agents.version),
+        };
+        let group = |builder: Self, category, disallow: &'static str| {
+            let mut tokens = agents.in_category(category).peekable();
+            if tokens.peek().is_none() {
+                return builder;
+            }
+            let mut section = SectionBuilder::new(builder).comment(comment.clone());
+            for token in tokens {
+                section = section.useragent(token.to_string());
+            }
+            section.disallow(disallow).end_section()
+        };
+        match preset {
+            Preset::BlockAiTraining => group(self, AgentCategory::AiTraining, "/"),
+            Preset::BlockAiAssistants => group(self, AgentCategory::AiAssistant, "/"),
+            Preset::BlockSeoTools => group(self, AgentCategory::SeoTool, "/"),
+            Preset::OnlySearchEngines => group(self, AgentCategory::SearchEngine, "")
+                .with_section("*", |section| {
+                    section.comment(comment.clone()).disallow("/")
+                }),
+            Preset::DisallowAll => self.with_section("*", |section| {
+                section.comment(comment.clone()).disallow("/")
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parts::Robots, registry::Crawler};
+
+    #[test]
+    fn presets() {
+        let mut agents = Registry::empty()
+            .crawler(Crawler::new("GPTBot", AgentCategory::AiTraining, "OpenAI"))
+            .crawler(Crawler::new(
+                "CCBot",
+                AgentCategory::AiTraining,
+                "Common Crawl",
+            ))
+            .crawler(Crawler::new(
+                "Googlebot",
+                AgentCategory::SearchEngine,
+                "Google",
+            ))
+            .crawler(Crawler::new("AhrefsBot", AgentCategory::SeoTool, "Ahrefs"));
+        agents.version = VERSION.to_string();
+        let robots = Robots::builder()
+            .preset_with(Preset::BlockAiTraining, &agents)
+            .preset_with(Preset::BlockAiAssistants, &agents)
+            .preset_with(Preset::OnlySearchEngines, &agents)
+            .build();
+        assert_eq!(
+            robots.to_string(),
+            format!(
+                "\
+# Preset: block AI training crawlers (agents {0})
+User-agent: CCBot
+User-agent: GPTBot
+Disallow: /
+
+# Preset: allow only search engines (agents {0})
+User-agent: Googlebot
+Disallow:
+
+# Preset: allow only search engines (agents {0})
+User-agent: *
+Disallow: /
+
+",
+                VERSION
+            )
+        );
+
+        let robots = Robots::builder()
+            .with_section("*", |section| section.disallow("/tmp"))
+            .preset(Preset::DisallowAll)
+            .build();
+        assert!(!robots.default_section.rules.is_empty());
+        assert!(
+            !crate::matcher::SimpleMatcher::new(&robots.default_section.rules)
+                .check_path("/index.html")
+        );
+    }
+}
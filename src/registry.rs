@@ -0,0 +1,504 @@
+//! A registry of known crawlers, to classify user agents and select their groups.
+//!
+//! The registry is kept in a plain text file, one crawler a line:
+//!
+//! ```text
+//! Version: 2026.10
+//! # token | category | operator | honours crawl-delay | fallback tokens
+//! Googlebot-Image | search | Google | no | Googlebot
+//! ```
+//!
+//! The bundled one comes with the crate, a newer one can be loaded with `Registry::parse`.
+//!
+//! ```
+//! use robots_txt::{registry::*, Robots};
+//!
+//! let registry = Registry::bundled();
+//! let ua = "Mozilla/5.0 (compatible; Googlebot-Image/1.0)";
+//! assert_eq!(Some("Googlebot-Image"), registry.classify(ua).map(|c| c.token.as_str()));
+//!
+//! let robots = Robots::from_str_lossy("User-agent: Googlebot\nDisallow: /private\n");
+//! let section = robots.choose_section_with(&registry, ua);
+//! assert!(!section.rules.is_empty());
+//! ```
+
+use std::{error::Error, fmt, str::FromStr, sync::OnceLock};
+
+use crate::parse::*;
+use crate::parts::*;
+
+/// The version of the bundled registry, bumped whenever it changes.
+pub const VERSION: &str = "2026.10";
+
+static BUNDLED: &str = include_str!("registry.txt");
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AgentCategory {
+    /// Crawlers of web search engines.
+    SearchEngine,
+    /// Crawlers collecting data to train AI models.
+    AiTraining,
+    /// Fetchers acting for a user of an AI assistant or AI search.
+    AiAssistant,
+    /// Crawlers of SEO and marketing tools.
+    SeoTool,
+    /// Link preview fetchers of social networks and messengers.
+    Social,
+    /// Uptime and performance monitors.
+    Monitoring,
+    /// Web archives.
+    Archiver,
+}
+
+impl AgentCategory {
+    /// The name used in registry files, e.g. `ai-training`.
+    pub fn name(self) -> &'static str {
+        match self {
+            AgentCategory::SearchEngine => "search",
+            AgentCategory::AiTraining => "ai-training",
+            AgentCategory::AiAssistant => "ai-assistant",
+            AgentCategory::SeoTool => "seo",
+            AgentCategory::Social => "social",
+            AgentCategory::Monitoring => "monitoring",
+            AgentCategory::Archiver => "archiver",
+        }
+    }
+}
+
+impl FromStr for AgentCategory {
+    type Err = RegistryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            AgentCategory::SearchEngine,
+            AgentCategory::AiTraining,
+            AgentCategory::AiAssistant,
+            AgentCategory::SeoTool,
+            AgentCategory::Social,
+            AgentCategory::Monitoring,
+            AgentCategory::Archiver,
+        ]
+        .iter()
+        .copied()
+        .find(|category| category.name().eq_ignore_ascii_case(s))
+        .ok_or_else(|| RegistryError::new(0, format!("unknown category `{}`", s)))
+    }
+}
+
+impl fmt::Display for AgentCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AgentCategory::SearchEngine => "search engine",
+            AgentCategory::AiTraining => "AI training",
+            AgentCategory::AiAssistant => "AI assistant",
+            AgentCategory::SeoTool => "SEO tool",
+            AgentCategory::Social => "social",
+            AgentCategory::Monitoring => "monitoring",
+            AgentCategory::Archiver => "archiver",
+        })
+    }
+}
+
+/// A crawler by the product token it looks for in robots.txt.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Crawler {
+    pub token: String,
+    pub category: AgentCategory,
+    pub operator: String,
+    /// Tokens whose groups the crawler obeys when no group lists its own, in order.
+    pub fallbacks: Vec<String>,
+    pub honours_crawl_delay: bool,
+}
+
+impl Crawler {
+    pub fn new<T, O>(token: T, category: AgentCategory, operator: O) -> Self
+    where
+        T: Into<String>,
+        O: Into<String>,
+    {
+        Crawler {
+            token: token.into(),
+            category,
+            operator: operator.into(),
+            fallbacks: Vec::new(),
+            honours_crawl_delay: false,
+        }
+    }
+
+    pub fn fallback<T>(mut self, token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.fallbacks.push(token.into());
+        self
+    }
+
+    pub fn honours_crawl_delay(mut self, honours: bool) -> Self {
+        self.honours_crawl_delay = honours;
+        self
+    }
+
+    /// The own token followed by the fallbacks.
+    pub fn tokens(&self) -> impl Iterator<Item = &str> {
+        Some(self.token.as_str())
+            .into_iter()
+            .chain(self.fallbacks.iter().map(String::as_str))
+    }
+}
+
+/// A problem in a registry file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegistryError {
+    /// 1-based.
+    pub line: usize,
+    pub message: String,
+}
+
+impl RegistryError {
+    fn new<M>(line: usize, message: M) -> Self
+    where
+        M: Into<String>,
+    {
+        RegistryError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for RegistryError {}
+
+/// Known crawlers, the bundled registry or a loaded one with local changes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Registry {
+    /// The version of the registry file, empty if it has none.
+    pub version: String,
+    crawlers: Vec<Crawler>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::bundled()
+    }
+}
+
+impl Registry {
+    /// The registry of this `VERSION`.
+    ///
+    /// It is parsed once, each call clones it.
+    pub fn bundled() -> Self {
+        static PARSED: OnceLock<Registry> = OnceLock::new();
+        PARSED
+            .get_or_init(|| Registry::parse(BUNDLED).expect("the bundled registry is valid"))
+            .clone()
+    }
+
+    pub fn empty() -> Self {
+        Registry {
+            version: String::new(),
+            crawlers: Vec::new(),
+        }
+    }
+
+    /// Reads a registry file, the format `Display` writes.
+    pub fn parse(input: &str) -> Result<Self, RegistryError> {
+        let mut registry = Registry::empty();
+        for (i, line) in input.lines().enumerate() {
+            let (line, _) = split_comment(line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: String| RegistryError::new(i + 1, message);
+            if !line.contains('|') {
+                match split_kv(line) {
+                    Some((k, v)) if k.eq_ignore_ascii_case("version") => {
+                        registry.version = v.to_string()
+                    }
+                    _ => return Err(error(format!("unexpected `{}`", line.trim()))),
+                }
+                continue;
+            }
+            let columns: Vec<_> = line.split('|').map(str::trim).collect();
+            if columns.len() != 5 {
+                return Err(error(format!(
+                    "expected 5 columns, found {}",
+                    columns.len()
+                )));
+            }
+            if columns[0].is_empty() || columns[0].contains(char::is_whitespace) {
+                return Err(error(format!("invalid token `{}`", columns[0])));
+            }
+            let category = columns[1]
+                .parse()
+                .map_err(|e: RegistryError| error(e.message))?;
+            let honours_crawl_delay = match columns[3] {
+                "yes" => true,
+                "no" => false,
+                other => return Err(error(format!("expected `yes` or `no`, found `{}`", other))),
+            };
+            if registry.get(columns[0]).is_some() {
+                return Err(error(format!("`{}` is listed twice", columns[0])));
+            }
+            registry.crawlers.push(Crawler {
+                token: columns[0].to_string(),
+                category,
+                operator: columns[2].to_string(),
+                fallbacks: columns[4].split_whitespace().map(String::from).collect(),
+                honours_crawl_delay,
+            });
+        }
+        Ok(registry)
+    }
+
+    pub fn crawlers(&self) -> &[Crawler] {
+        &self.crawlers
+    }
+
+    /// Finds a crawler by its token, ignoring case.
+    pub fn get(&self, token: &str) -> Option<&Crawler> {
+        self.crawlers
+            .iter()
+            .find(|crawler| crawler.token.eq_ignore_ascii_case(token))
+    }
+
+    /// The tokens of the crawlers in the category, in the order of the registry.
+    pub fn in_category(&self, category: AgentCategory) -> impl Iterator<Item = &str> {
+        self.crawlers
+            .iter()
+            .filter(move |crawler| crawler.category == category)
+            .map(|crawler| crawler.token.as_str())
+    }
+
+    /// Finds the crawler a `User-Agent` header belongs to.
+    ///
+    /// A product token in the header matching a listed token exactly wins,
+    /// otherwise one starting with a listed token and `-`, e.g. `Googlebot-Mobile`.
+    /// The longest listed token is preferred either way.
+    pub fn classify(&self, user_agent: &str) -> Option<&Crawler> {
+        let words: Vec<_> = user_agent
+            .split(|c: char| !(c.is_ascii_alphanumeric() || "-_.".contains(c)))
+            .filter(|word| !word.is_empty())
+            .collect();
+        let longest = |matches: &dyn Fn(&str, &str) -> bool| {
+            self.crawlers
+                .iter()
+                .filter(|crawler| words.iter().any(|word| matches(word, &crawler.token)))
+                .max_by_key(|crawler| crawler.token.len())
+        };
+        longest(&|word, token| word.eq_ignore_ascii_case(token)).or_else(|| {
+            longest(&|word, token| {
+                word.len() > token.len()
+                    && word.as_bytes()[token.len()] == b'-'
+                    && word[..token.len()].eq_ignore_ascii_case(token)
+            })
+        })
+    }
+
+    /// Adds a crawler, or replaces the listed one with the same token.
+    pub fn crawler(mut self, crawler: Crawler) -> Self {
+        match self
+            .crawlers
+            .iter_mut()
+            .find(|other| other.token.eq_ignore_ascii_case(&crawler.token))
+        {
+            Some(other) => *other = crawler,
+            None => self.crawlers.push(crawler),
+        }
+        self
+    }
+
+    /// Moves a listed crawler to another category, does nothing for an unknown one.
+    pub fn recategorize(mut self, token: &str, category: AgentCategory) -> Self {
+        if let Some(crawler) = self
+            .crawlers
+            .iter_mut()
+            .find(|crawler| crawler.token.eq_ignore_ascii_case(token))
+        {
+            crawler.category = category;
+        }
+        self
+    }
+
+    pub fn remove(mut self, token: &str) -> Self {
+        self.crawlers
+            .retain(|crawler| !crawler.token.eq_ignore_ascii_case(token));
+        self
+    }
+
+    /// Takes the crawlers and the version of a newer registry, keeping the crawlers only listed here.
+    pub fn update(self, newer: Registry) -> Self {
+        let version = newer.version.clone();
+        let mut registry = newer.crawlers.into_iter().fold(self, Registry::crawler);
+        registry.version = version;
+        registry
+    }
+}
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.version.is_empty() {
+            writeln!(f, "Version: {}", self.version)?;
+        }
+        writeln!(
+            f,
+            "# token | category | operator | honours crawl-delay | fallback tokens"
+        )?;
+        for crawler in &self.crawlers {
+            writeln!(
+                f,
+                "{} | {} | {} | {} | {}",
+                crawler.token,
+                crawler.category.name(),
+                crawler.operator,
+                match crawler.honours_crawl_delay {
+                    true => "yes",
+                    false => "no",
+                },
+                crawler.fallbacks.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Robots<'a> {
+    /// Returns the group the crawler obeys: the one listing its token,
+    /// else the one listing its first listed fallback, else the default group.
+    pub fn choose_section_for(&self, crawler: &Crawler) -> &Section<'a> {
+        crawler
+            .tokens()
+            .filter(|token| *token != "*")
+            .find_map(|token| self.find_group(token))
+            .unwrap_or(&self.default_section)
+    }
+
+    /// Classifies a `User-Agent` header with the registry to choose the group,
+    /// falling back to `choose_section` for crawlers the registry doesn't know.
+    pub fn choose_section_with(&self, registry: &Registry, user_agent: &str) -> &Section<'a> {
+        match registry.classify(user_agent) {
+            Some(crawler) => self.choose_section_for(crawler),
+            None => self.choose_section(user_agent),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled() {
+        let registry = Registry::bundled();
+        assert_eq!(VERSION, registry.version);
+        assert_eq!(Ok(registry.clone()), Registry::parse(&registry.to_string()));
+        assert_eq!(
+            Some(AgentCategory::AiTraining),
+            registry.get("gptbot").map(|crawler| crawler.category)
+        );
+        assert!(registry.get("ClaudeBot").unwrap().honours_crawl_delay);
+        assert!(registry
+            .in_category(AgentCategory::Social)
+            .any(|t| t == "Twitterbot"));
+
+        let registry = registry
+            .recategorize("PerplexityBot", AgentCategory::AiTraining)
+            .remove("Amazonbot")
+            .crawler(Crawler::new(
+                "ExampleBot",
+                AgentCategory::AiTraining,
+                "Example",
+            ));
+        let training: Vec<_> = registry.in_category(AgentCategory::AiTraining).collect();
+        assert!(training.contains(&"PerplexityBot"));
+        assert!(training.contains(&"ExampleBot"));
+        assert!(!training.contains(&"Amazonbot"));
+
+        let newer = Registry::parse(
+            "Version: 2027.01\nExampleBot | seo | Example | yes |\nNewBot | social | New | no |\n",
+        )
+        .unwrap();
+        let registry = registry.update(newer);
+        assert_eq!("2027.01", registry.version);
+        assert_eq!(
+            AgentCategory::SeoTool,
+            registry.get("ExampleBot").unwrap().category
+        );
+        assert!(registry.get("NewBot").is_some() && registry.get("Googlebot").is_some());
+
+        assert_eq!(
+            Err(RegistryError::new(2, "unknown category `search-engine`")),
+            Registry::parse("# comment\nBot | search-engine | Someone | no |\n")
+        );
+        assert_eq!(
+            Err(RegistryError::new(
+                1,
+                "expected `yes` or `no`, found `maybe`"
+            )),
+            Registry::parse("Bot | search | Someone | maybe |\n")
+        );
+    }
+
+    #[test]
+    fn classify() {
+        let registry = Registry::bundled();
+        let token = |ua| registry.classify(ua).map(|crawler| crawler.token.as_str());
+        assert_eq!(
+            Some("Googlebot"),
+            token(
+                "Mozilla/5.0 (Linux; Android 6.0.1; Nexus 5X Build/MMB29P) AppleWebKit/537.36 \
+                 (KHTML, like Gecko) Chrome/130.0.0.0 Mobile Safari/537.36 \
+                 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"
+            )
+        );
+        assert_eq!(Some("Googlebot-Image"), token("Googlebot-Image/1.0"));
+        assert_eq!(Some("Googlebot"), token("Googlebot-Mobile/2.1"));
+        assert_eq!(
+            Some("ChatGPT-User"),
+            token(
+                "Mozilla/5.0 AppleWebKit/537.36 (KHTML, like Gecko); compatible; \
+                 ChatGPT-User/1.0; +https://openai.com/bot"
+            )
+        );
+        assert_eq!(
+            Some("Bingbot"),
+            token("Mozilla/5.0 (compatible; bingbot/2.0; +http://www.bing.com/bingbot.htm)")
+        );
+        assert_eq!(
+            None,
+            token("Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0")
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let robots = Robots::from_str_lossy(
+            "\
+User-agent: *
+Disallow: /private
+
+User-agent: Googlebot
+Disallow: /nogoogle
+
+User-agent: Googlebot-Image
+Disallow: /photos
+",
+        );
+        let registry = Registry::bundled();
+        let path = |ua| {
+            robots.choose_section_with(&registry, ua).rules[0]
+                .path
+                .to_string()
+        };
+        assert_eq!("/photos", path("Googlebot-Image/1.0"));
+        assert_eq!("/nogoogle", path("Googlebot-News"));
+        assert_eq!("/nogoogle", path("Mozilla/5.0 (Macintosh) Applebot/0.1"));
+        assert_eq!("/private", path("Mozilla/5.0 (compatible; GPTBot/1.2)"));
+        assert_eq!("/private", path("curl/8.0"));
+    }
+}
@@ -0,0 +1,554 @@
+use std::{collections::BTreeSet, fmt, io};
+
+use url::Url;
+
+use crate::parts::*;
+
+/// The order in which groups are written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GroupOrder {
+    /// Named groups in their original order, the `*` group last.
+    NamedFirst,
+    /// The `*` group first, then named groups in their original order.
+    DefaultFirst,
+    /// Named groups sorted by their first user agent, the `*` group last.
+    Sorted,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// How directive names are capitalized.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Capitalization {
+    /// `User-agent`, `Crawl-delay`, ...
+    Canonical,
+    /// `User-Agent`, `Crawl-Delay`, ...
+    Title,
+    /// `user-agent`, `crawl-delay`, ...
+    Lower,
+    /// `USER-AGENT`, `CRAWL-DELAY`, ...
+    Upper,
+}
+
+/// Where blank lines are put.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlankLines {
+    /// A blank line after every group, including the last one.
+    AfterEachGroup,
+    /// A blank line between groups only.
+    BetweenGroups,
+    /// No blank lines at all.
+    None,
+}
+
+/// Where `Sitemap` and `Host` lines are put.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Placement {
+    /// Inside the group they were declared in. `Host` has no group and is put at the end.
+    InGroup,
+    /// Before the first group.
+    Start,
+    /// After the last group.
+    End,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Directive {
+    UserAgent,
+    Allow,
+    Disallow,
+    CrawlDelay,
+    RequestRate,
+    VisitTime,
+    ContentSignal,
+    ContentUsage,
+    Sitemap,
+    Host,
+}
+
+impl Directive {
+    pub fn name(self, capitalization: Capitalization) -> &'static str {
+        use self::{Capitalization::*, Directive::*};
+        match (self, capitalization) {
+            (UserAgent, Canonical) => "User-agent",
+            (UserAgent, Title) => "User-Agent",
+            (UserAgent, Lower) => "user-agent",
+            (UserAgent, Upper) => "USER-AGENT",
+            (Allow, Canonical) | (Allow, Title) => "Allow",
+            (Allow, Lower) => "allow",
+            (Allow, Upper) => "ALLOW",
+            (Disallow, Canonical) | (Disallow, Title) => "Disallow",
+            (Disallow, Lower) => "disallow",
+            (Disallow, Upper) => "DISALLOW",
+            (CrawlDelay, Canonical) => "Crawl-delay",
+            (CrawlDelay, Title) => "Crawl-Delay",
+            (CrawlDelay, Lower) => "crawl-delay",
+            (CrawlDelay, Upper) => "CRAWL-DELAY",
+            (RequestRate, Canonical) => "Request-rate",
+            (RequestRate, Title) => "Request-Rate",
+            (RequestRate, Lower) => "request-rate",
+            (RequestRate, Upper) => "REQUEST-RATE",
+            (VisitTime, Canonical) => "Visit-time",
+            (VisitTime, Title) => "Visit-Time",
+            (VisitTime, Lower) => "visit-time",
+            (VisitTime, Upper) => "VISIT-TIME",
+            (ContentSignal, Canonical) | (ContentSignal, Title) => "Content-Signal",
+            (ContentSignal, Lower) => "content-signal",
+            (ContentSignal, Upper) => "CONTENT-SIGNAL",
+            (ContentUsage, Canonical) | (ContentUsage, Title) => "Content-Usage",
+            (ContentUsage, Lower) => "content-usage",
+            (ContentUsage, Upper) => "CONTENT-USAGE",
+            (Sitemap, Canonical) | (Sitemap, Title) => "Sitemap",
+            (Sitemap, Lower) => "sitemap",
+            (Sitemap, Upper) => "SITEMAP",
+            (Host, Canonical) | (Host, Title) => "Host",
+            (Host, Lower) => "host",
+            (Host, Upper) => "HOST",
+        }
+    }
+}
+
+/// Options for [`Robots::render_with`](../parts/robots/struct.Robots.html#method.render_with).
+///
+/// The default options produce the same output as `Display for Robots`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RenderOptions {
+    pub group_order: GroupOrder,
+    pub line_ending: LineEnding,
+    pub capitalization: Capitalization,
+    pub blank_lines: BlankLines,
+    pub sitemaps: Placement,
+    pub host: Placement,
+    pub header: Vec<String>,
+    pub footer: Vec<String>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            group_order: GroupOrder::NamedFirst,
+            line_ending: LineEnding::Lf,
+            capitalization: Capitalization::Canonical,
+            blank_lines: BlankLines::AfterEachGroup,
+            sitemaps: Placement::InGroup,
+            host: Placement::End,
+            header: Vec::new(),
+            footer: Vec::new(),
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        RenderOptions::default()
+    }
+
+    pub fn group_order(mut self, order: GroupOrder) -> Self {
+        self.group_order = order;
+        self
+    }
+
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    pub fn capitalization(mut self, capitalization: Capitalization) -> Self {
+        self.capitalization = capitalization;
+        self
+    }
+
+    pub fn blank_lines(mut self, blank_lines: BlankLines) -> Self {
+        self.blank_lines = blank_lines;
+        self
+    }
+
+    pub fn sitemaps(mut self, placement: Placement) -> Self {
+        self.sitemaps = placement;
+        self
+    }
+
+    pub fn host(mut self, placement: Placement) -> Self {
+        self.host = placement;
+        self
+    }
+
+    /// Adds a comment line to the block written before everything else.
+    pub fn header<S>(mut self, line: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.header.push(line.into());
+        self
+    }
+
+    /// Adds a comment line to the block written after everything else.
+    pub fn footer<S>(mut self, line: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.footer.push(line.into());
+        self
+    }
+}
+
+pub(crate) struct Renderer<'o, W> {
+    out: W,
+    options: &'o RenderOptions,
+}
+
+impl<'o, W> Renderer<'o, W>
+where
+    W: fmt::Write,
+{
+    pub fn new(out: W, options: &'o RenderOptions) -> Self {
+        Renderer { out, options }
+    }
+
+    fn end_line(&mut self) -> fmt::Result {
+        self.out.write_str(self.options.line_ending.as_str())
+    }
+
+    fn blank_line(&mut self) -> fmt::Result {
+        self.end_line()
+    }
+
+    fn directive<V>(&mut self, directive: Directive, value: V) -> fmt::Result
+    where
+        V: fmt::Display,
+    {
+        self.directive_with_comment(directive, value, None)
+    }
+
+    fn directive_with_comment<V>(
+        &mut self,
+        directive: Directive,
+        value: V,
+        comment: Option<&str>,
+    ) -> fmt::Result
+    where
+        V: fmt::Display,
+    {
+        self.out
+            .write_str(directive.name(self.options.capitalization))?;
+        self.out.write_char(':')?;
+        self.value(value)?;
+        if let Some(comment) = comment {
+            self.out.write_str(" #")?;
+            // A trailing comment must stay on its line.
+            for word in comment.split_whitespace() {
+                self.out.write_char(' ')?;
+                self.out.write_str(word)?;
+            }
+        }
+        self.end_line()
+    }
+
+    /// Writes ` value`, or nothing if the value renders empty.
+    fn value<V>(&mut self, value: V) -> fmt::Result
+    where
+        V: fmt::Display,
+    {
+        let value = value.to_string();
+        if !value.is_empty() {
+            self.out.write_char(' ')?;
+            self.out.write_str(&value)?;
+        }
+        Ok(())
+    }
+
+    fn comment_block<I>(&mut self, comments: I) -> fmt::Result
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        for comment in comments {
+            for line in comment.as_ref().trim_end_matches('\n').split('\n') {
+                let line = line.trim_end();
+                self.out.write_char('#')?;
+                if !line.is_empty() {
+                    self.out.write_char(' ')?;
+                    self.out.write_str(line)?;
+                }
+                self.end_line()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sitemaps<'u, I>(&mut self, sitemaps: I) -> fmt::Result
+    where
+        I: IntoIterator<Item = &'u Url>,
+    {
+        for url in sitemaps {
+            self.directive(Directive::Sitemap, url)?;
+        }
+        Ok(())
+    }
+
+    pub fn rule(&mut self, rule: &Rule<'_>) -> fmt::Result {
+        self.comment_block(&rule.comments)?;
+        let directive = match rule.allow {
+            true => Directive::Allow,
+            false => Directive::Disallow,
+        };
+        self.directive_with_comment(directive, &rule.path, rule.trailing_comment.as_deref())
+    }
+
+    pub fn section(&mut self, section: &Section<'_>) -> fmt::Result {
+        self.comment_block(&section.comments)?;
+        for ua in &section.useragents {
+            self.directive(Directive::UserAgent, ua)?;
+        }
+        for rule in &section.rules {
+            self.rule(rule)?;
+        }
+        if let Some(delay) = section.crawl_delay.as_ref() {
+            self.directive(Directive::CrawlDelay, delay)?;
+        }
+        if let Some(rate) = section.req_rate.as_ref() {
+            self.directive(
+                Directive::RequestRate,
+                format_args!("{}/{}", rate.requests, rate.seconds),
+            )?;
+        }
+        if let Some(visit_time) = section.visit_time.as_ref() {
+            self.directive(Directive::VisitTime, visit_time)?;
+        }
+        for pref in &section.usage {
+            let directive = match pref.directive {
+                UsageDirective::ContentSignal => Directive::ContentSignal,
+                UsageDirective::ContentUsage => Directive::ContentUsage,
+            };
+            self.directive(directive, pref)?;
+        }
+        if self.options.sitemaps == Placement::InGroup {
+            self.sitemaps(&section.sitemaps)?;
+        }
+        Ok(())
+    }
+
+    pub fn robots(&mut self, robots: &Robots<'_>) -> fmt::Result {
+        let options = self.options;
+        self.comment_block(&options.header)?;
+        self.comment_block(&robots.comments)?;
+        if !robots.comments.is_empty() && options.blank_lines != BlankLines::None {
+            self.blank_line()?;
+        }
+
+        let sitemaps: BTreeSet<&Url> = robots
+            .sections
+            .iter()
+            .chain(Some(&robots.default_section))
+            .flat_map(|section| &section.sitemaps)
+            .collect();
+        let host = robots.host.as_ref();
+
+        if options.host == Placement::Start {
+            if let Some(host) = host {
+                self.directive(Directive::Host, host)?;
+            }
+        }
+        if options.sitemaps == Placement::Start {
+            self.sitemaps(sitemaps.iter().cloned())?;
+        }
+
+        let mut sections: Vec<&Section> = robots.sections.iter().collect();
+        if options.group_order == GroupOrder::Sorted {
+            sections.sort_by(|a, b| a.useragents.iter().next().cmp(&b.useragents.iter().next()));
+        }
+        match options.group_order {
+            GroupOrder::DefaultFirst => sections.insert(0, &robots.default_section),
+            GroupOrder::NamedFirst | GroupOrder::Sorted => sections.push(&robots.default_section),
+        }
+        sections.retain(|section| !section.is_empty());
+
+        for (i, section) in sections.iter().enumerate() {
+            if i > 0 && options.blank_lines == BlankLines::BetweenGroups {
+                self.blank_line()?;
+            }
+            self.section(section)?;
+            if options.blank_lines == BlankLines::AfterEachGroup {
+                self.blank_line()?;
+            }
+        }
+
+        if options.sitemaps == Placement::End {
+            self.sitemaps(sitemaps.iter().cloned())?;
+        }
+        if options.host != Placement::Start {
+            if let Some(host) = host {
+                self.directive(Directive::Host, host)?;
+            }
+        }
+        self.comment_block(&options.footer)
+    }
+}
+
+/// Adapts an `io::Write` to `fmt::Write`, keeping the underlying io error.
+pub(crate) struct IoAdapter<W> {
+    pub inner: W,
+    pub error: Option<io::Error>,
+}
+
+impl<W> fmt::Write for IoAdapter<W>
+where
+    W: io::Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ROBOTS: &str = r#"
+User-Agent: *
+Disallow: /private
+Sitemap: http://example.com/sitemap.xml
+
+User-Agent: zbot
+Disallow: /z
+Crawl-delay: 2
+
+User-Agent: abot
+Disallow: /a
+Request-rate: 1/5
+Sitemap: http://example.com/a.xml
+
+Host: example.com
+"#;
+
+    #[test]
+    fn default_matches_display() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        assert_eq!(
+            robots.to_string(),
+            robots.render_with(&RenderOptions::default())
+        );
+    }
+
+    #[test]
+    fn options() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        let options = RenderOptions::new()
+            .group_order(GroupOrder::DefaultFirst)
+            .line_ending(LineEnding::CrLf)
+            .capitalization(Capitalization::Title)
+            .blank_lines(BlankLines::BetweenGroups)
+            .sitemaps(Placement::End)
+            .host(Placement::Start)
+            .header("robots.txt for example.com")
+            .footer("")
+            .footer("generated");
+        let expected = "\
+# robots.txt for example.com\r
+Host: example.com\r
+User-Agent: *\r
+Disallow: /private\r
+\r
+User-Agent: zbot\r
+Disallow: /z\r
+Crawl-Delay: 2\r
+\r
+User-Agent: abot\r
+Disallow: /a\r
+Request-Rate: 1/5\r
+Sitemap: http://example.com/a.xml\r
+Sitemap: http://example.com/sitemap.xml\r
+#\r
+# generated\r
+";
+        assert_eq!(expected, robots.render_with(&options));
+    }
+
+    #[test]
+    fn sorted_compact() {
+        let robots = Robots::from_str_lossy(ROBOTS);
+        let options = RenderOptions::new()
+            .group_order(GroupOrder::Sorted)
+            .capitalization(Capitalization::Lower)
+            .blank_lines(BlankLines::None)
+            .sitemaps(Placement::Start);
+        let expected = "\
+sitemap: http://example.com/a.xml
+sitemap: http://example.com/sitemap.xml
+user-agent: abot
+disallow: /a
+request-rate: 1/5
+user-agent: zbot
+disallow: /z
+crawl-delay: 2
+user-agent: *
+disallow: /private
+host: example.com
+";
+        assert_eq!(expected, robots.render_with(&options));
+    }
+
+    #[test]
+    fn comments() {
+        let mut robots = Robots::from_str_lossy(ROBOTS);
+        robots
+            .comments
+            .push("Do not edit.\nGenerated by a tool.".into());
+        robots.sections[0]
+            .comments
+            .push("Owned by the z team".into());
+        robots.sections[0].rules[0].comments.push("Legacy".into());
+        robots.sections[0].rules[0].trailing_comment = Some("see\nticket 42".into());
+        let options = RenderOptions::new()
+            .header("robots.txt for example.com")
+            .blank_lines(BlankLines::BetweenGroups);
+        let expected = "\
+# robots.txt for example.com
+# Do not edit.
+# Generated by a tool.
+
+# Owned by the z team
+User-agent: zbot
+# Legacy
+Disallow: /z # see ticket 42
+Crawl-delay: 2
+
+User-agent: abot
+Disallow: /a
+Request-rate: 1/5
+Sitemap: http://example.com/a.xml
+
+User-agent: *
+Disallow: /private
+Sitemap: http://example.com/sitemap.xml
+Host: example.com
+";
+        assert_eq!(expected, robots.render_with(&options));
+    }
+
+    #[test]
+    fn io_write() {
+        let robots = Robots::default();
+        let mut out = Vec::new();
+        robots
+            .render_io(&mut out, &RenderOptions::default())
+            .unwrap();
+        assert_eq!(b"User-agent: *\nDisallow:\n\n", &out[..]);
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    fetch::Response,
+    parts::{Robots, Section, VisitTime},
+};
+
+/// How often a host may be visited, as its robots.txt group asks.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Politeness {
+    /// The pause between two requests, the longer of `Crawl-delay` and `Request-rate`.
+    pub delay: Option<Duration>,
+    pub visit_time: Option<VisitTime>,
+}
+
+impl Politeness {
+    pub fn from_section(section: &Section<'_>) -> Self {
+        let crawl_delay = section
+            .crawl_delay
+            .filter(|delay| delay.is_finite() && *delay >= 0.)
+            .and_then(|delay| Duration::try_from_secs_f64(delay).ok());
+        let request_rate = section
+            .req_rate
+            .filter(|rate| rate.requests > 0)
+            .map(|rate| rate.into_duration());
+        Politeness {
+            delay: crawl_delay.max(request_rate),
+            visit_time: section.visit_time,
+        }
+    }
+
+    /// Takes the group which applies to this user agent.
+    pub fn from_robots(robots: &Robots<'_>, ua: &str) -> Self {
+        Politeness::from_section(robots.choose_section(ua))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchedulerConfig {
+    /// The pause between requests to a host whose robots.txt sets none.
+    pub default_delay: Duration,
+    /// Caps the pause a robots.txt may ask for, `None` to honour any.
+    pub max_delay: Option<Duration>,
+    /// The pause after the first `429` or `503`, doubled on each one in a row.
+    pub backoff: Duration,
+    /// Caps both the backoff and `Retry-After`.
+    pub max_backoff: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            default_delay: Duration::from_secs(1),
+            max_delay: None,
+            backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn new() -> Self {
+        SchedulerConfig::default()
+    }
+
+    pub fn default_delay(mut self, delay: Duration) -> Self {
+        self.default_delay = delay;
+        self
+    }
+
+    pub fn max_delay(mut self, delay: Option<Duration>) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+struct HostState {
+    politeness: Politeness,
+    /// The earliest time of the next request.
+    next: SystemTime,
+    /// `429` and `503` responses in a row.
+    failures: u32,
+}
+
+/// Spaces out requests to each host.
+///
+/// Hosts are arbitrary keys, a crawler may use origins as well.
+pub struct Scheduler {
+    config: SchedulerConfig,
+    clock: Arc<dyn Clock>,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler::with_config(SchedulerConfig::default())
+    }
+
+    pub fn with_config(config: SchedulerConfig) -> Self {
+        Scheduler {
+            config,
+            clock: Arc::new(SystemClock),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// Sets the policy of a host, typically after its robots.txt was fetched.
+    pub fn set_politeness(&self, host: &str, politeness: Politeness) {
+        let now = self.clock.now();
+        self.lock()
+            .entry(host.to_string())
+            .or_insert_with(|| HostState {
+                politeness,
+                next: now,
+                failures: 0,
+            })
+            .politeness = politeness;
+    }
+
+    pub fn politeness(&self, host: &str) -> Option<Politeness> {
+        self.lock().get(host).map(|state| state.politeness)
+    }
+
+    /// Forgets a host, its next request is permitted right away.
+    pub fn remove(&self, host: &str) {
+        self.lock().remove(host);
+    }
+
+    /// The earliest time a request to this host is permitted, without claiming it.
+    pub fn next_fetch(&self, host: &str) -> SystemTime {
+        let now = self.clock.now();
+        match self.lock().get(host) {
+            Some(state) => slot(state, now),
+            None => now,
+        }
+    }
+
+    /// Claims the next permitted request to this host, returning how long to wait for it.
+    ///
+    /// Each call claims a later slot, so concurrent workers don't hit a host at once.
+    pub fn reserve(&self, host: &str) -> Duration {
+        let now = self.clock.now();
+        let mut hosts = self.lock();
+        let state = hosts.entry(host.to_string()).or_insert_with(|| HostState {
+            politeness: Politeness::default(),
+            next: now,
+            failures: 0,
+        });
+        let slot = slot(state, now);
+        state.next = slot + self.delay(&state.politeness);
+        slot.duration_since(now).unwrap_or_default()
+    }
+
+    /// Claims the next request and sleeps until it is permitted.
+    pub fn wait(&self, host: &str) {
+        let wait = self.reserve(host);
+        if wait > Duration::from_secs(0) {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Claims the next request, sleeping with the runtime's timer,
+    /// e.g. `scheduler.wait_async(host, tokio::time::sleep).await`.
+    pub fn wait_async<S, F>(&self, host: &str, sleep: S) -> F
+    where
+        S: FnOnce(Duration) -> F,
+        F: Future<Output = ()>,
+    {
+        sleep(self.reserve(host))
+    }
+
+    /// Adjusts the pace by the response status, backing off on `429` and `503`.
+    pub fn record(&self, host: &str, status: u16, retry_after: Option<&str>) {
+        let now = self.clock.now();
+        let mut hosts = self.lock();
+        let state = match hosts.get_mut(host) {
+            Some(state) => state,
+            None if status == 429 || status == 503 => {
+                hosts.entry(host.to_string()).or_insert_with(|| HostState {
+                    politeness: Politeness::default(),
+                    next: now,
+                    failures: 0,
+                })
+            }
+            None => return,
+        };
+        if status != 429 && status != 503 {
+            state.failures = 0;
+            return;
+        }
+        state.failures = state.failures.saturating_add(1);
+        let pause = retry_after
+            .and_then(|value| parse_retry_after(value, now))
+            .unwrap_or_else(|| {
+                self.config
+                    .backoff
+                    .checked_mul(1 << (state.failures - 1).min(31))
+                    .unwrap_or(self.config.max_backoff)
+            })
+            .min(self.config.max_backoff);
+        state.next = state.next.max(now + pause);
+    }
+
+    pub fn record_response(&self, host: &str, response: &Response) {
+        self.record(host, response.status, response.find_header("retry-after"))
+    }
+
+    fn delay(&self, politeness: &Politeness) -> Duration {
+        let delay = politeness.delay.unwrap_or(self.config.default_delay);
+        match self.config.max_delay {
+            Some(max_delay) => delay.min(max_delay),
+            None => delay,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, HostState>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn slot(state: &HostState, now: SystemTime) -> SystemTime {
+    let slot = state.next.max(now);
+    match state.politeness.visit_time {
+        Some(visit_time) => slot + visit_time.wait_from(slot),
+        None => slot,
+    }
+}
+
+/// The pause asked by `Retry-After`, either in seconds or as an HTTP date.
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = parse_http_date(value)?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+/// Parses the IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let mut parts = value.split_whitespace();
+    parts.next()?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':').map(|n| n.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" || year < 1970 || day == 0 || day > 31 {
+        return None;
+    }
+    // Days from the civil date, shifting the year to start in March.
+    let (y, m) = match month > 2 {
+        true => (year, month - 3),
+        false => (year - 1, month + 9),
+    };
+    let days = 365 * y + y / 4 - y / 100 + y / 400 + (153 * m + 2) / 5 + day - 1 - 719_468;
+    let secs = days * 86_400 + hours * 3600 + minutes * 60 + seconds;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU64, Ordering},
+        task::{Context, Poll, Waker},
+    };
+
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn scheduler() -> (Scheduler, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(UNIX_EPOCH + secs(100 * 86_400)));
+        (Scheduler::new().clock(clock.clone()), clock)
+    }
+
+    #[test]
+    fn politeness() {
+        let robots = Robots::from_str_lossy(
+            "User-agent: a\nCrawl-delay: 2\nRequest-rate: 1/5\n\n\
+             User-agent: *\nCrawl-delay: 0.5\nVisit-time: 0600-0845\n",
+        );
+        assert_eq!(Some(secs(5)), Politeness::from_robots(&robots, "a").delay);
+        let other = Politeness::from_robots(&robots, "b");
+        assert_eq!(Some(Duration::from_millis(500)), other.delay);
+        assert_eq!(Some(VisitTime::new(360, 525)), other.visit_time);
+    }
+
+    #[test]
+    fn delays() {
+        let (scheduler, clock) = scheduler();
+        assert_eq!(secs(0), scheduler.reserve("a.com"));
+        assert_eq!(secs(1), scheduler.reserve("a.com"));
+        assert_eq!(secs(0), scheduler.reserve("b.com"));
+
+        scheduler.set_politeness(
+            "c.com",
+            Politeness {
+                delay: Some(secs(10)),
+                visit_time: None,
+            },
+        );
+        assert_eq!(secs(0), scheduler.reserve("c.com"));
+        assert_eq!(secs(10), scheduler.reserve("c.com"));
+        assert_eq!(secs(20), scheduler.reserve("c.com"));
+        clock.advance(secs(25));
+        assert_eq!(secs(5), scheduler.reserve("c.com"));
+        assert_eq!(clock.now() + secs(15), scheduler.next_fetch("c.com"));
+    }
+
+    #[test]
+    fn visit_time() {
+        let (scheduler, clock) = scheduler();
+        scheduler.set_politeness(
+            "a.com",
+            Politeness {
+                delay: Some(secs(60)),
+                visit_time: Some(VisitTime::new(60, 62)),
+            },
+        );
+        assert_eq!(secs(3600), scheduler.reserve("a.com"));
+        assert_eq!(secs(3660), scheduler.reserve("a.com"));
+        // The window is closed by the third slot, which moves to the next day.
+        assert_eq!(secs(86_400 + 3600), scheduler.reserve("a.com"));
+        clock.advance(secs(86_400 + 3600));
+        assert_eq!(secs(60), scheduler.reserve("a.com"));
+    }
+
+    #[test]
+    fn backoff() {
+        let (scheduler, clock) = scheduler();
+        scheduler.reserve("a.com");
+        scheduler.record("a.com", 503, None);
+        assert_eq!(clock.now() + secs(30), scheduler.next_fetch("a.com"));
+        scheduler.record("a.com", 429, None);
+        assert_eq!(clock.now() + secs(60), scheduler.next_fetch("a.com"));
+        scheduler.record("a.com", 200, None);
+        clock.advance(secs(60));
+        scheduler.record("a.com", 503, None);
+        assert_eq!(clock.now() + secs(30), scheduler.next_fetch("a.com"));
+
+        scheduler.record_response(
+            "b.com",
+            &Response::new(429).with_header("Retry-After", "120"),
+        );
+        assert_eq!(clock.now() + secs(120), scheduler.next_fetch("b.com"));
+        scheduler.record("c.com", 503, Some("999999"));
+        assert_eq!(clock.now() + secs(3600), scheduler.next_fetch("c.com"));
+    }
+
+    #[test]
+    fn retry_after() {
+        let now = UNIX_EPOCH + secs(784_111_700);
+        assert_eq!(Some(secs(77)), parse_retry_after("77", now));
+        assert_eq!(
+            Some(secs(77)),
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now)
+        );
+        assert_eq!(
+            Some(secs(0)),
+            parse_retry_after("Thu, 01 Jan 1970 00:00:00 GMT", now)
+        );
+        assert_eq!(None, parse_retry_after("soon", now));
+    }
+
+    #[test]
+    fn wait_async() {
+        let (scheduler, _) = scheduler();
+        let slept = Arc::new(AtomicU64::new(u64::MAX));
+        scheduler.reserve("a.com");
+        let future = scheduler.wait_async("a.com", |duration| {
+            let slept = slept.clone();
+            async move { slept.store(duration.as_secs(), Ordering::SeqCst) }
+        });
+        let mut future = Box::pin(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Poll::Ready(()), future.as_mut().poll(&mut cx));
+        assert_eq!(1, slept.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    cache::{CachedPolicy, Origin},
+    fetch::{body_hash, Availability, Validators},
+    matcher::RobotsMatcher,
+    parse::split_kv,
+    parts::Robots,
+    payload::{PayloadDiagnostic, PayloadHandling, PayloadKind},
+};
+
+/// A policy with its fetch metadata, as it is persisted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StoredPolicy {
+    pub origin: Origin,
+    pub fetched_at: SystemTime,
+    pub expires_at: SystemTime,
+    pub status: Option<u16>,
+    pub availability: Availability,
+    pub validators: Validators,
+    pub unreachable_since: Option<SystemTime>,
+    pub last_good_at: Option<SystemTime>,
+    pub diagnostic: Option<PayloadDiagnostic>,
+    /// The hash of the robots.txt as it was fetched, see `FetchResult::source_hash`.
+    pub source_hash: Option<u64>,
+    /// The `body_hash` of `robots`, checked on load.
+    pub checksum: u64,
+    /// The policy rendered as robots.txt.
+    pub robots: String,
+}
+
+impl StoredPolicy {
+    pub fn from_cached(origin: Origin, policy: &CachedPolicy) -> Self {
+        let robots = policy.matcher.robots().to_string();
+        StoredPolicy {
+            origin,
+            fetched_at: policy.fetched_at,
+            expires_at: policy.expires_at,
+            status: policy.status,
+            availability: policy.availability,
+            validators: policy.validators.clone(),
+            unreachable_since: policy.unreachable_since,
+            last_good_at: policy.last_good_at,
+            diagnostic: policy.diagnostic,
+            source_hash: policy.source_hash,
+            checksum: body_hash(robots.as_bytes()),
+            robots,
+        }
+    }
+
+    pub fn to_cached(&self) -> CachedPolicy {
+        let robots = Robots::from_str_lossy(&self.robots).into_owned();
+        CachedPolicy {
+            matcher: Arc::new(RobotsMatcher::new(robots)),
+            availability: self.availability,
+            status: self.status,
+            fetched_at: self.fetched_at,
+            expires_at: self.expires_at,
+            validators: self.validators.clone(),
+            unreachable_since: self.unreachable_since,
+            last_good_at: self.last_good_at,
+            diagnostic: self.diagnostic,
+            source_hash: self.source_hash,
+        }
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = String::from("robots-store: 2\n");
+        let mut field = |name: &str, value: &dyn ToString| {
+            out.push_str(name);
+            out.push_str(": ");
+            out.push_str(&value.to_string());
+            out.push('\n');
+        };
+        field("scheme", &self.origin.scheme);
+        field("host", &self.origin.host);
+        field("port", &self.origin.port);
+        field("fetched-at", &seconds(self.fetched_at));
+        field("expires-at", &seconds(self.expires_at));
+        if let Some(status) = self.status {
+            field("status", &status);
+        }
+        field("availability", &availability_name(self.availability));
+        if let Some(etag) = self.validators.etag.as_ref() {
+            field("etag", etag);
+        }
+        if let Some(last_modified) = self.validators.last_modified.as_ref() {
+            field("last-modified", last_modified);
+        }
+        if let Some(since) = self.unreachable_since {
+            field("unreachable-since", &seconds(since));
+        }
+        if let Some(at) = self.last_good_at {
+            field("last-good-at", &seconds(at));
+        }
+        if let Some(diagnostic) = self.diagnostic {
+            field("payload-kind", &payload_kind_name(diagnostic.kind));
+            field(
+                "payload-handling",
+                &payload_handling_name(diagnostic.handling),
+            );
+        }
+        if let Some(hash) = self.source_hash {
+            field("source-hash", &format!("{:016x}", hash));
+        }
+        field("checksum", &format!("{:016x}", self.checksum));
+        out.push('\n');
+        out.push_str(&self.robots);
+        out
+    }
+
+    /// `None` for a file in another format or with a damaged policy.
+    fn deserialize(input: &str) -> Option<StoredPolicy> {
+        let split = input.find("\n\n")?;
+        let (header, robots) = (&input[..split], &input[split + 2..]);
+        let mut lines = header.lines();
+        if lines.next()? != "robots-store: 2" {
+            return None;
+        }
+        let (mut scheme, mut host, mut port) = (None, None, None);
+        let (mut fetched_at, mut expires_at, mut availability) = (None, None, None);
+        let (mut payload_kind, mut payload_handling) = (None, None);
+        let mut policy = StoredPolicy {
+            origin: Origin {
+                scheme: String::new(),
+                host: String::new(),
+                port: 0,
+            },
+            fetched_at: UNIX_EPOCH,
+            expires_at: UNIX_EPOCH,
+            status: None,
+            availability: Availability::Available,
+            validators: Validators::default(),
+            unreachable_since: None,
+            last_good_at: None,
+            diagnostic: None,
+            source_hash: None,
+            checksum: 0,
+            robots: robots.to_string(),
+        };
+        for line in lines {
+            let (key, value) = split_kv(line)?;
+            match key {
+                "scheme" => scheme = Some(value.to_string()),
+                "host" => host = Some(value.to_string()),
+                "port" => port = Some(value.parse().ok()?),
+                "fetched-at" => fetched_at = Some(time(value)?),
+                "expires-at" => expires_at = Some(time(value)?),
+                "status" => policy.status = Some(value.parse().ok()?),
+                "availability" => availability = Some(parse_availability(value)?),
+                "etag" => policy.validators.etag = Some(value.to_string()),
+                "last-modified" => policy.validators.last_modified = Some(value.to_string()),
+                "unreachable-since" => policy.unreachable_since = Some(time(value)?),
+                "last-good-at" => policy.last_good_at = Some(time(value)?),
+                "payload-kind" => payload_kind = Some(parse_payload_kind(value)?),
+                "payload-handling" => payload_handling = Some(parse_payload_handling(value)?),
+                "source-hash" => policy.source_hash = Some(u64::from_str_radix(value, 16).ok()?),
+                "checksum" => policy.checksum = u64::from_str_radix(value, 16).ok()?,
+                _ => {}
+            }
+        }
+        policy.origin = Origin {
+            scheme: scheme?,
+            host: host?,
+            port: port?,
+        };
+        policy.fetched_at = fetched_at?;
+        policy.expires_at = expires_at?;
+        policy.availability = availability?;
+        // Files written before `last-good-at` hold the last good copy if they were available.
+        if policy.availability == Availability::Available && policy.last_good_at.is_none() {
+            policy.last_good_at = Some(policy.fetched_at);
+        }
+        policy.diagnostic = match (payload_kind, payload_handling) {
+            (Some(kind), Some(handling)) => Some(PayloadDiagnostic { kind, handling }),
+            (None, None) => None,
+            _ => return None,
+        };
+        match body_hash(policy.robots.as_bytes()) == policy.checksum {
+            true => Some(policy),
+            false => None,
+        }
+    }
+}
+
+/// Persists policies between runs, a second tier behind `PolicyCache`.
+pub trait PolicyStore: Send + Sync {
+    fn load(&self, origin: &Origin) -> io::Result<Option<StoredPolicy>>;
+    fn save(&self, policy: &StoredPolicy) -> io::Result<()>;
+    fn remove(&self, origin: &Origin) -> io::Result<()>;
+}
+
+/// Keeps one file per origin in a directory.
+///
+/// Files are replaced atomically, so a crash leaves either the old or the new version.
+#[derive(Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+    counter: AtomicUsize,
+}
+
+impl FileStore {
+    /// Opens the directory, creating it if needed.
+    pub fn open<P>(dir: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(FileStore {
+            dir,
+            counter: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, origin: &Origin) -> PathBuf {
+        let host: String = origin
+            .host
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+                _ => '_',
+            })
+            .collect();
+        self.dir
+            .join(format!("{}_{}_{}.robots", origin.scheme, host, origin.port))
+    }
+}
+
+impl PolicyStore for FileStore {
+    fn load(&self, origin: &Origin) -> io::Result<Option<StoredPolicy>> {
+        let input = match fs::read_to_string(self.path(origin)) {
+            Ok(input) => input,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        // Different origins may share a file name, only the matching one counts.
+        Ok(StoredPolicy::deserialize(&input).filter(|policy| policy.origin == *origin))
+    }
+
+    fn save(&self, policy: &StoredPolicy) -> io::Result<()> {
+        let path = self.path(&policy.origin);
+        let tmp = path.with_extension(format!(
+            "tmp.{}.{}",
+            std::process::id(),
+            self.counter.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = (|| {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(policy.serialize().as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp, &path)?;
+            // Make the rename itself durable, where directories can be synced.
+            if let Ok(dir) = fs::File::open(&self.dir) {
+                dir.sync_all().ok();
+            }
+            Ok(())
+        })();
+        if result.is_err() {
+            fs::remove_file(&tmp).ok();
+        }
+        result
+    }
+
+    fn remove(&self, origin: &Origin) -> io::Result<()> {
+        match fs::remove_file(self.path(origin)) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn time(value: &str) -> Option<SystemTime> {
+    value
+        .parse()
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn availability_name(availability: Availability) -> &'static str {
+    match availability {
+        Availability::Available => "available",
+        Availability::Unavailable => "unavailable",
+        Availability::Unreachable => "unreachable",
+    }
+}
+
+fn parse_availability(value: &str) -> Option<Availability> {
+    match value {
+        "available" => Some(Availability::Available),
+        "unavailable" => Some(Availability::Unavailable),
+        "unreachable" => Some(Availability::Unreachable),
+        _ => None,
+    }
+}
+
+fn payload_kind_name(kind: PayloadKind) -> &'static str {
+    match kind {
+        PayloadKind::Text => "text",
+        PayloadKind::Html => "html",
+        PayloadKind::Json => "json",
+        PayloadKind::Binary => "binary",
+    }
+}
+
+fn parse_payload_kind(value: &str) -> Option<PayloadKind> {
+    match value {
+        "text" => Some(PayloadKind::Text),
+        "html" => Some(PayloadKind::Html),
+        "json" => Some(PayloadKind::Json),
+        "binary" => Some(PayloadKind::Binary),
+        _ => None,
+    }
+}
+
+fn payload_handling_name(handling: PayloadHandling) -> &'static str {
+    match handling {
+        PayloadHandling::AllowAll => "allow-all",
+        PayloadHandling::DisallowAll => "disallow-all",
+        PayloadHandling::Parse => "parse",
+    }
+}
+
+fn parse_payload_handling(value: &str) -> Option<PayloadHandling> {
+    match value {
+        "allow-all" => Some(PayloadHandling::AllowAll),
+        "disallow-all" => Some(PayloadHandling::DisallowAll),
+        "parse" => Some(PayloadHandling::Parse),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "robots_txt-{}-{}-{}",
+            name,
+            std::process::id(),
+            seconds(SystemTime::now())
+        ));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn policy() -> StoredPolicy {
+        let robots = "User-agent: *\nDisallow: /private\n\n".to_string();
+        StoredPolicy {
+            origin: Origin {
+                scheme: "https".into(),
+                host: "example.com".into(),
+                port: 443,
+            },
+            fetched_at: UNIX_EPOCH + Duration::from_secs(1_000),
+            expires_at: UNIX_EPOCH + Duration::from_secs(87_400),
+            status: Some(200),
+            availability: Availability::Available,
+            validators: Validators {
+                etag: Some("\"v1\"".into()),
+                last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()),
+            },
+            unreachable_since: None,
+            last_good_at: Some(UNIX_EPOCH + Duration::from_secs(1_000)),
+            diagnostic: None,
+            source_hash: Some(body_hash(b"User-agent: *\r\nDisallow: /private\r\n")),
+            checksum: body_hash(robots.as_bytes()),
+            robots,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let dir = temp_dir("roundtrip");
+        let store = FileStore::open(&dir).unwrap();
+        let policy = policy();
+        assert_eq!(None, store.load(&policy.origin).unwrap());
+
+        store.save(&policy).unwrap();
+        assert_eq!(Some(&policy), store.load(&policy.origin).unwrap().as_ref());
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(1, files.len());
+
+        let cached = policy.to_cached();
+        assert!(!cached.check_path("bot", "/private"));
+        assert_eq!(
+            policy,
+            StoredPolicy::from_cached(policy.origin.clone(), &cached)
+        );
+
+        let policy = StoredPolicy {
+            diagnostic: Some(PayloadDiagnostic {
+                kind: PayloadKind::Html,
+                handling: PayloadHandling::DisallowAll,
+            }),
+            ..policy
+        };
+        store.save(&policy).unwrap();
+        assert_eq!(Some(&policy), store.load(&policy.origin).unwrap().as_ref());
+        assert_eq!(policy.diagnostic, policy.to_cached().diagnostic);
+
+        store.remove(&policy.origin).unwrap();
+        assert_eq!(None, store.load(&policy.origin).unwrap());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn damaged() {
+        let dir = temp_dir("damaged");
+        let store = FileStore::open(&dir).unwrap();
+        let policy = policy();
+        store.save(&policy).unwrap();
+        let path = store.path(&policy.origin);
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace("/private", "/public")).unwrap();
+        assert_eq!(None, store.load(&policy.origin).unwrap());
+
+        fs::write(&path, "garbage").unwrap();
+        assert_eq!(None, store.load(&policy.origin).unwrap());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,442 @@
+//! A robots.txt with placeholders, filled in per tenant.
+//!
+//! Placeholders stand for values and name their kind the first time they appear,
+//! `{{name:kind}}`, where the kind is `host`, `url`, `path` or `number`:
+//!
+//! ```text
+//! User-agent: *
+//! Disallow: {{private:path}}
+//! Sitemap: https://{{host:host}}/sitemap.xml
+//!
+//! Host: {{host}}
+//! ```
+//!
+//! A template is checked once when it is parsed, values once when they are set,
+//! so rendering is only joining strings.
+
+use std::{error::Error, fmt};
+
+use url::Url;
+
+use crate::{builder::RobotsBuilder, parse::*, parts::*};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PlaceholderKind {
+    /// A host name with an optional port, e.g. `example.com:8080`.
+    Host,
+    /// An absolute URL.
+    Url,
+    /// A path pattern starting with `/` or `*`.
+    Path,
+    /// A non-negative number, e.g. for `Crawl-delay`.
+    Number,
+}
+
+impl PlaceholderKind {
+    fn parse(input: &str) -> Option<Self> {
+        match input {
+            "host" => Some(PlaceholderKind::Host),
+            "url" => Some(PlaceholderKind::Url),
+            "path" => Some(PlaceholderKind::Path),
+            "number" => Some(PlaceholderKind::Number),
+            _ => None,
+        }
+    }
+
+    /// A valid value, to check the template with.
+    fn sample(self) -> &'static str {
+        match self {
+            PlaceholderKind::Host => "example.com",
+            PlaceholderKind::Url => "http://example.com/",
+            PlaceholderKind::Path => "/",
+            PlaceholderKind::Number => "1",
+        }
+    }
+
+    pub fn accepts(self, value: &str) -> bool {
+        if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '#') {
+            return false;
+        }
+        match self {
+            PlaceholderKind::Host => {
+                let (name, port) = match value.rfind(':') {
+                    Some(pos) => (&value[..pos], Some(&value[pos + 1..])),
+                    None => (value, None),
+                };
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '-' || c == '.')
+                    && port.is_none_or(|port| port.parse::<u16>().is_ok())
+            }
+            PlaceholderKind::Url => Url::parse(value).is_ok(),
+            PlaceholderKind::Path => PathPattern::parse(value).is_ok(),
+            PlaceholderKind::Number => value.parse::<f64>().is_ok_and(|n| n.is_finite() && n >= 0.),
+        }
+    }
+}
+
+impl fmt::Display for PlaceholderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlaceholderKind::Host => "host",
+            PlaceholderKind::Url => "url",
+            PlaceholderKind::Path => "path",
+            PlaceholderKind::Number => "number",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    pub kind: PlaceholderKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateError {
+    /// A mistake in the template itself, the line is 1-based.
+    Syntax { line: usize, message: String },
+    /// The template doesn't give a valid robots.txt.
+    Invalid(String),
+    /// No value for a placeholder.
+    Missing(String),
+    /// A value for a name which is not a placeholder of the template.
+    Unknown(String),
+    /// A value not of the kind of its placeholder.
+    InvalidValue {
+        name: String,
+        kind: PlaceholderKind,
+        value: String,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            TemplateError::Invalid(message) => write!(f, "invalid template: {}", message),
+            TemplateError::Missing(name) => write!(f, "no value for `{}`", name),
+            TemplateError::Unknown(name) => write!(f, "unknown placeholder `{}`", name),
+            TemplateError::InvalidValue { name, kind, value } => {
+                write!(f, "`{}` is not a valid {} for `{}`", value, kind, name)
+            }
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    /// The index of the placeholder.
+    Value(usize),
+}
+
+/// A robots.txt with typed placeholders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RobotsTemplate {
+    segments: Vec<Segment>,
+    placeholders: Vec<Placeholder>,
+}
+
+impl RobotsTemplate {
+    /// Parses a template, checking that any valid values give a valid robots.txt.
+    pub fn parse(input: &str) -> Result<Self, TemplateError> {
+        let mut lines = Vec::new();
+        for (i, line) in input.split_inclusive('\n').enumerate() {
+            let syntax = |message| TemplateError::Syntax {
+                line: i + 1,
+                message,
+            };
+            if let Some((key, _)) = split_kv(split_comment(line).0) {
+                if key.contains("{{") {
+                    return Err(syntax("placeholders may only stand for values".into()));
+                }
+            }
+            lines.push((line, placeholders_in(line).map_err(syntax)?));
+        }
+
+        let mut template = RobotsTemplate {
+            segments: Vec::new(),
+            placeholders: Vec::new(),
+        };
+        for (i, (_, found)) in lines.iter().enumerate() {
+            for &(_, _, name, kind) in found {
+                match (template.position(name), kind) {
+                    (Some(index), Some(kind)) if template.placeholders[index].kind != kind => {
+                        return Err(TemplateError::Syntax {
+                            line: i + 1,
+                            message: format!(
+                                "`{}` is already a {}",
+                                name, template.placeholders[index].kind
+                            ),
+                        });
+                    }
+                    (None, Some(kind)) => template.placeholders.push(Placeholder {
+                        name: name.to_string(),
+                        kind,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        for (i, (line, found)) in lines.iter().enumerate() {
+            let mut last = 0;
+            for &(start, end, name, _) in found {
+                let index = match template.position(name) {
+                    Some(index) => index,
+                    None => {
+                        return Err(TemplateError::Syntax {
+                            line: i + 1,
+                            message: format!(
+                                "`{}` has no kind, write `{{{{{}:kind}}}}`",
+                                name, name
+                            ),
+                        })
+                    }
+                };
+                template.text(&line[last..start]);
+                template.segments.push(Segment::Value(index));
+                last = end;
+            }
+            template.text(&line[last..]);
+        }
+
+        let sample = template.fill(|index| template.placeholders[index].kind.sample());
+        let robots = Robots::from_str_lossy(&sample);
+        if let Err(errors) = RobotsBuilder::from(robots).try_build() {
+            return Err(TemplateError::Invalid(errors.to_string()));
+        }
+        Ok(template)
+    }
+
+    pub fn placeholders(&self) -> &[Placeholder] {
+        &self.placeholders
+    }
+
+    /// Checks the values for a tenant, every placeholder needs one.
+    pub fn instantiate<'v, I>(&self, values: I) -> Result<TenantRobots, TemplateError>
+    where
+        I: IntoIterator<Item = (&'v str, &'v str)>,
+    {
+        let mut filled: Vec<Option<&str>> = vec![None; self.placeholders.len()];
+        for (name, value) in values {
+            let index = match self.position(name) {
+                Some(index) => index,
+                None => return Err(TemplateError::Unknown(name.to_string())),
+            };
+            let kind = self.placeholders[index].kind;
+            if !kind.accepts(value) {
+                return Err(TemplateError::InvalidValue {
+                    name: name.to_string(),
+                    kind,
+                    value: value.to_string(),
+                });
+            }
+            filled[index] = Some(value);
+        }
+        if let Some(index) = filled.iter().position(Option::is_none) {
+            return Err(TemplateError::Missing(
+                self.placeholders[index].name.clone(),
+            ));
+        }
+        let text = self.fill(|index| filled[index].unwrap_or_default());
+        Ok(TenantRobots { text })
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.placeholders
+            .iter()
+            .position(|placeholder| placeholder.name == name)
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        match self.segments.last_mut() {
+            Some(Segment::Text(last)) => last.push_str(text),
+            _ => self.segments.push(Segment::Text(text.to_string())),
+        }
+    }
+
+    fn fill<'s, F>(&self, mut value: F) -> String
+    where
+        F: FnMut(usize) -> &'s str,
+    {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Value(index) => out.push_str(value(*index)),
+            }
+        }
+        out
+    }
+}
+
+/// A template filled in for one tenant, rendered once.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TenantRobots {
+    text: String,
+}
+
+impl TenantRobots {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn robots(&self) -> Robots<'_> {
+        Robots::from_str_lossy(&self.text)
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl fmt::Display for TenantRobots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// A placeholder in a line: where it starts and ends, its name and its kind if given.
+type Found<'a> = (usize, usize, &'a str, Option<PlaceholderKind>);
+
+fn placeholders_in(line: &str) -> Result<Vec<Found<'_>>, String> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = line[offset..].find("{{") {
+        let start = offset + start;
+        let end = match line[start..].find("}}") {
+            Some(end) => start + end + 2,
+            None => return Err("unclosed `{{`".into()),
+        };
+        let inner = line[start + 2..end - 2].trim();
+        let (name, kind) = match inner.find(':') {
+            Some(pos) => (inner[..pos].trim(), Some(inner[pos + 1..].trim())),
+            None => (inner, None),
+        };
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err(format!("invalid placeholder name `{}`", name));
+        }
+        let kind = match kind {
+            Some(kind) => match PlaceholderKind::parse(kind) {
+                Some(kind) => Some(kind),
+                None => return Err(format!("unknown kind `{}`", kind)),
+            },
+            None => None,
+        };
+        found.push((start, end, name, kind));
+        offset = end;
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEMPLATE: &str = r#"# robots.txt for {{host:host}}
+User-agent: *
+Disallow: {{private:path}}
+Disallow: /tmp/
+Crawl-delay: {{delay:number}}
+Sitemap: https://{{host}}/sitemap.xml
+
+Host: {{host}}
+"#;
+
+    #[test]
+    fn instantiate() {
+        let template = RobotsTemplate::parse(TEMPLATE).unwrap();
+        assert_eq!(
+            vec!["host", "private", "delay"],
+            template
+                .placeholders()
+                .iter()
+                .map(|placeholder| placeholder.name.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let tenant = template
+            .instantiate(vec![
+                ("host", "shop.example.com"),
+                ("private", "/admin/"),
+                ("delay", "2.5"),
+            ])
+            .unwrap();
+        assert_eq!(
+            tenant.as_str(),
+            r#"# robots.txt for shop.example.com
+User-agent: *
+Disallow: /admin/
+Disallow: /tmp/
+Crawl-delay: 2.5
+Sitemap: https://shop.example.com/sitemap.xml
+
+Host: shop.example.com
+"#
+        );
+        let robots = tenant.robots();
+        assert_eq!(Some(2.5), robots.default_section.crawl_delay);
+        assert_eq!(Some("shop.example.com"), robots.host.as_deref());
+    }
+
+    #[test]
+    fn invalid_values() {
+        let template = RobotsTemplate::parse(TEMPLATE).unwrap();
+        let test =
+            |values: Vec<(&str, &str)>| template.instantiate(values).unwrap_err().to_string();
+        assert_eq!(
+            "no value for `delay`",
+            test(vec![("host", "a.com"), ("private", "/p")])
+        );
+        assert_eq!(
+            "`a.com/x` is not a valid host for `host`",
+            test(vec![("host", "a.com/x")])
+        );
+        assert_eq!(
+            "`private` is not a valid path for `private`",
+            test(vec![("private", "private")])
+        );
+        assert_eq!(
+            "`/p\nAllow: /` is not a valid path for `private`",
+            test(vec![("private", "/p\nAllow: /")])
+        );
+        assert_eq!("unknown placeholder `port`", test(vec![("port", "80")]));
+    }
+
+    #[test]
+    fn invalid_templates() {
+        let test = |input: &str| RobotsTemplate::parse(input).unwrap_err().to_string();
+        assert_eq!(
+            "line 2: unclosed `{{`",
+            test("User-agent: *\nDisallow: {{path:path\n")
+        );
+        assert_eq!(
+            "line 1: unknown kind `text`",
+            test("User-agent: {{agent:text}}\n")
+        );
+        assert_eq!(
+            "line 2: placeholders may only stand for values",
+            test("User-agent: *\n{{rule:path}}: /\n")
+        );
+        assert_eq!(
+            "line 2: `p` has no kind, write `{{p:kind}}`",
+            test("User-agent: *\nDisallow: {{p}}\n")
+        );
+        assert_eq!(
+            "line 3: `p` is already a path",
+            test("User-agent: *\nDisallow: {{p:path}}\nHost: {{p:host}}\n")
+        );
+        assert_eq!(
+            "invalid template: group `*`: invalid path `x/`: must start with `/` or `*`",
+            test("User-agent: *\nDisallow: x{{p:path}}\n")
+        );
+    }
+}